@@ -0,0 +1,297 @@
+use alloc::vec::Vec;
+use num_traits::Float;
+use crate::extrapolation::{extrapolate, Extrapolation};
+use crate::spline::GcvSpline;
+use crate::woltring::search::{find_knot_interval, guess_knot_interval};
+use crate::woltring::splder::evaluate_spline;
+use crate::woltring::support::FittingError;
+
+/// Represents the antiderivative of a GCV spline, which is 0 at the first knot. Between knots it is a polynomial of
+/// degree 2 * *m*, so it is stored as the integrals of the spline up to each knot rather than as another GCV spline.
+/// A GCV spline of half-order *m* + 1 cannot represent it exactly: such a spline has continuous derivatives up to
+/// order 2 * *m* at its knots, while the derivative of order 2 * *m* of the antiderivative, the derivative of order
+/// 2 * *m* - 1 of the spline, jumps at every knot. It is evaluated like a [`GcvSpline`], and outside the knots it
+/// follows the [`Extrapolation`] of the spline, applied to the antiderivative itself so that its derivatives agree
+/// with its values.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Antiderivative<T: Float> {
+    spline: GcvSpline<T>,
+    cumulative: Vec<T>,
+    rule: Vec<(T, T)>,
+}
+
+impl<T: Float> Antiderivative<T> {
+
+    /// Evaluates the antiderivative at a single point.
+    pub fn single_point(&self, point: T) -> T {
+        self.point_derivative(point, 0)
+    }
+
+    /// Evaluates the antiderivative at a set of points.
+    pub fn points(&self, points: &[T]) -> Vec<T> {
+        self.derivative(points, 0)
+    }
+
+    /// Evaluates a derivative of a given order of the antiderivative at a single point. The first derivative is the
+    /// original spline. Points outside the knots are handled according to the [`Extrapolation`] of the spline.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> T {
+        let knots = &self.spline.knots;
+        extrapolate(self.spline.extrapolation, knots[0], knots[knots.len() - 1], point, derivative_order,
+                    |point, derivative_order| self.evaluate_piecewise(point, derivative_order))
+            .unwrap_or_else(|| self.evaluate_piecewise(point, derivative_order))
+    }
+
+    /// Evaluates a derivative of a given order of the antiderivative at a set of points.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        points.iter().map(|point| self.point_derivative(*point, derivative_order)).collect()
+    }
+
+    /// Evaluates the antiderivative at each point yielded by `points`, writing the values into `output` in the same
+    /// order. Returns the number of values written; points beyond the length of `output` are not evaluated.
+    pub fn points_into<I: IntoIterator<Item = T>>(&self, points: I, output: &mut [T]) -> usize {
+        self.derivative_into(points, 0, output)
+    }
+
+    /// Evaluates a derivative of a given order of the antiderivative at each point yielded by `points`, writing the
+    /// values into `output` in the same order. Returns the number of values written; points beyond the length of
+    /// `output` are not evaluated.
+    pub fn derivative_into<I: IntoIterator<Item = T>>(&self, points: I, derivative_order: usize, output: &mut [T])
+        -> usize {
+        let mut written = 0;
+        for (value, point) in output.iter_mut().zip(points) {
+            *value = self.point_derivative(point, derivative_order);
+            written += 1;
+        }
+        written
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` of the antiderivative at a single point, in
+    /// increasing order of derivative.
+    pub fn evaluate_all_derivatives(&self, point: T, max_order: usize) -> Vec<T> {
+        (0 ..= max_order).map(|derivative_order| self.point_derivative(point, derivative_order)).collect()
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` of the antiderivative at a set of points. The result
+    /// holds one vector per derivative order, starting with the values.
+    pub fn all_derivatives(&self, points: &[T], max_order: usize) -> Vec<Vec<T>> {
+        (0 ..= max_order).map(|derivative_order| self.derivative(points, derivative_order)).collect()
+    }
+
+    /// Evaluates the first derivative of the antiderivative, the original spline, at a set of points.
+    pub fn first_derivative(&self, points: &[T]) -> Vec<T> {
+        self.derivative(points, 1)
+    }
+
+    /// Evaluates the second derivative of the antiderivative at a set of points.
+    pub fn second_derivative(&self, points: &[T]) -> Vec<T> {
+        self.derivative(points, 2)
+    }
+
+    /// Evaluates the third derivative of the antiderivative at a set of points.
+    pub fn third_derivative(&self, points: &[T]) -> Vec<T> {
+        self.derivative(points, 3)
+    }
+
+    /// Evaluates the antiderivative at a single point, returning [`FittingError::PointOutOfRange`] if the point is not
+    /// finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_single_point(&self, point: T) -> Result<T, FittingError> {
+        self.try_point_derivative(point, 0)
+    }
+
+    /// Evaluates the antiderivative at a set of points, returning [`FittingError::PointOutOfRange`] if any point is
+    /// not finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_points(&self, points: &[T]) -> Result<Vec<T>, FittingError> {
+        self.try_derivative(points, 0)
+    }
+
+    /// Evaluates a derivative of a given order of the antiderivative at a single point, returning
+    /// [`FittingError::PointOutOfRange`] if the point is not finite or lies outside the knots with
+    /// [`Extrapolation::Error`].
+    pub fn try_point_derivative(&self, point: T, derivative_order: usize) -> Result<T, FittingError> {
+        self.spline.check_point(point)?;
+        Ok(self.point_derivative(point, derivative_order))
+    }
+
+    /// Evaluates a derivative of a given order of the antiderivative at a set of points, returning
+    /// [`FittingError::PointOutOfRange`] if any point is not finite or lies outside the knots with
+    /// [`Extrapolation::Error`].
+    pub fn try_derivative(&self, points: &[T], derivative_order: usize) -> Result<Vec<T>, FittingError> {
+        points.iter().map(|point| self.try_point_derivative(*point, derivative_order)).collect()
+    }
+
+    /// Returns the antiderivative with the provided extrapolation outside the range of the knots. This also sets the
+    /// extrapolation of the spline returned by [`Antiderivative::spline`].
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.spline.extrapolation = extrapolation;
+        self
+    }
+
+    /// Returns how the antiderivative is evaluated outside the range of the knots.
+    pub fn extrapolation(&self) -> Extrapolation {
+        self.spline.extrapolation
+    }
+
+    /// Returns the knots (time points) of the spline.
+    pub fn knots(&self) -> Vec<T> {
+        self.spline.knots()
+    }
+
+    /// Returns the spline this is the antiderivative of.
+    pub fn spline(&self) -> &GcvSpline<T> {
+        &self.spline
+    }
+
+    /// Evaluates a derivative of a given order of the polynomial pieces of the antiderivative, which continue past
+    /// the knots.
+    fn evaluate_piecewise(&self, point: T, derivative_order: usize) -> T {
+        if derivative_order > 0 {
+            return self.spline.evaluate_piecewise(point, derivative_order - 1);
+        }
+        let knots = &self.spline.knots;
+        let knot_interval = find_knot_interval(knots, point, guess_knot_interval(knots, point));
+        let start = knot_interval.max(1) - 1;
+        self.cumulative[start] + self.spline.integrate_piece(knots[start], point, knot_interval, &self.rule)
+    }
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Computes the definite integral of the spline from `start` to `end`. Each polynomial piece is integrated exactly
+    /// with Gauss-Legendre quadrature. The integral is negative if `end` is smaller than `start`.
+    pub fn integral(&self, start: T, end: T) -> T {
+        if end < start {
+            return -self.integral(end, start);
+        }
+        let first = find_knot_interval(&self.knots, start, guess_knot_interval(&self.knots, start));
+        let last = find_knot_interval(&self.knots, end, guess_knot_interval(&self.knots, end));
+        let rule = gauss_legendre(self.half_order);
+
+        // Split the integration range at the knots inside it
+        let mut integral = T::from(0.).expect("Cannot convert to type from f64");
+        let mut lower = start;
+        for knot_index in first .. last {
+            integral = integral + self.integrate_piece(lower, self.knots[knot_index], knot_index, &rule);
+            lower = self.knots[knot_index];
+        }
+        integral + self.integrate_piece(lower, end, last, &rule)
+    }
+
+    /// Returns the antiderivative of the spline, which is 0 at the first knot and follows the extrapolation of the
+    /// spline outside the knots.
+    pub fn antiderivative(&self) -> Antiderivative<T> {
+        let rule = gauss_legendre(self.half_order);
+        let mut cumulative = Vec::with_capacity(self.knots.len());
+        cumulative.push(T::from(0.).expect("Cannot convert to type from f64"));
+        for knot_index in 1 .. self.knots.len() {
+            cumulative.push(cumulative[knot_index - 1]
+                + self.integrate_piece(self.knots[knot_index - 1], self.knots[knot_index], knot_index, &rule));
+        }
+        Antiderivative {
+            spline: self.clone(),
+            cumulative,
+            rule,
+        }
+    }
+
+    /// Integrates the spline between two points within the knot interval `knot_interval`, where it is a polynomial
+    /// of degree 2 * *m* - 1, so that the *m* Gauss-Legendre nodes and weights in `rule` are exact.
+    fn integrate_piece(&self, start: T, end: T, knot_interval: usize, rule: &[(T, T)]) -> T {
+        let half_width = (end - start) / T::from(2.).expect("Cannot convert to type from f64");
+        let middle = start + half_width;
+        rule.iter()
+            .fold(T::from(0.).expect("Cannot convert to type from f64"), |sum, (node, weight)| {
+                sum + *weight * evaluate_spline(0, self.half_order, middle + half_width * *node, &self.knots,
+                                                &self.coefficients, knot_interval)
+            }) * half_width
+    }
+}
+
+/// Computes the nodes and weights of the Gauss-Legendre rule with `num_nodes` nodes on [-1, 1], which integrates
+/// polynomials of degree up to 2 * `num_nodes` - 1 exactly. The nodes are found by Newton iteration on the Legendre
+/// polynomial.
+pub(crate) fn gauss_legendre<T: Float>(num_nodes: usize) -> Vec<(T, T)> {
+    let one = T::from(1.).expect("Cannot convert to type from f64");
+    let mut rule = Vec::with_capacity(num_nodes);
+    for index in 1 ..= num_nodes {
+        let mut node = (T::from(core::f64::consts::PI).expect("Cannot convert to type from f64")
+            * (T::from(index).expect("Cannot convert to type from usize")
+            - T::from(0.25).expect("Cannot convert to type from f64"))
+            / (T::from(num_nodes).expect("Cannot convert to type from usize")
+            + T::from(0.5).expect("Cannot convert to type from f64"))).cos();
+        let mut slope = one;
+        for _ in 0 .. 100 {
+            // Evaluate the Legendre polynomial and its derivative by recurrence
+            let mut previous = one;
+            let mut value = node;
+            for degree in 2 ..= num_nodes {
+                let degree_t = T::from(degree).expect("Cannot convert to type from usize");
+                let next = ((degree_t + degree_t - one) * node * value - (degree_t - one) * previous) / degree_t;
+                previous = value;
+                value = next;
+            }
+            slope = T::from(num_nodes).expect("Cannot convert to type from usize") * (node * value - previous)
+                / (node * node - one);
+            let step = value / slope;
+            node = node - step;
+            if step.abs() <= T::epsilon() {
+                break;
+            }
+        }
+        rule.push((node, (one + one) / ((one - node * node) * slope * slope)));
+    }
+    rule
+}
+
+/// Nodes of the 15-point Kronrod rule on [0, 1], with the nodes of the embedded 7-point Gauss rule at odd indices.
+const KRONROD_NODES: [f64; 8] = [0.9914553711208126, 0.9491079123427585, 0.8648644233597691, 0.7415311855993945,
+                                 0.5860872354676911, 0.4058451513773972, 0.20778495500789848, 0.];
+const KRONROD_WEIGHTS: [f64; 8] = [0.022935322010529224, 0.06309209262997856, 0.10479001032225019,
+                                   0.14065325971552592, 0.1690047266392679, 0.19035057806478542,
+                                   0.20443294007529889, 0.20948214108472782];
+const GAUSS_WEIGHTS: [f64; 4] = [0.1294849661688697, 0.27970539148927664, 0.3818300505051189, 0.4179591836734694];
+
+/// Integrates `integrand` from `start` to `end` with adaptive Gauss-Kronrod quadrature, to within
+/// `relative_tolerance` of the integral where possible. Each interval is integrated with the 15-point Kronrod rule,
+/// whose difference from the embedded 7-point Gauss rule estimates the error, and intervals whose error exceeds their
+/// share of the tolerance are bisected, at most `max_depth` times.
+pub(crate) fn adaptive_gauss_kronrod<T: Float, F: Fn(T) -> T>(integrand: &F, start: T, end: T, relative_tolerance: T,
+                                                              max_depth: usize) -> T {
+    let (integral, error) = gauss_kronrod(integrand, start, end);
+    refine_gauss_kronrod(integrand, start, end, integral, error, relative_tolerance * integral.abs(), max_depth)
+}
+
+/// Bisects an interval whose Gauss-Kronrod `integral` has an `error` above `tolerance`, until the error of each part
+/// is within its share of the tolerance or 50 machine epsilons of its integral.
+fn refine_gauss_kronrod<T: Float, F: Fn(T) -> T>(integrand: &F, start: T, end: T, integral: T, error: T, tolerance: T,
+                                                 max_depth: usize) -> T {
+    let two = T::from(2.).expect("Cannot convert to type from f64");
+    let middle = start + (end - start) / two;
+    if !error.is_finite() || error <= tolerance
+        || error <= T::from(50.).expect("Cannot convert to type from f64") * T::epsilon() * integral.abs()
+        || max_depth == 0 || middle <= start || middle >= end {
+        return integral;
+    }
+    let (lower, lower_error) = gauss_kronrod(integrand, start, middle);
+    let (upper, upper_error) = gauss_kronrod(integrand, middle, end);
+    refine_gauss_kronrod(integrand, start, middle, lower, lower_error, tolerance / two, max_depth - 1)
+        + refine_gauss_kronrod(integrand, middle, end, upper, upper_error, tolerance / two, max_depth - 1)
+}
+
+/// Integrates `integrand` from `start` to `end` with the 15-point Kronrod rule, returning the integral and an estimate
+/// of its error from the embedded 7-point Gauss rule.
+fn gauss_kronrod<T: Float, F: Fn(T) -> T>(integrand: &F, start: T, end: T) -> (T, T) {
+    let half_width = (end - start) / T::from(2.).expect("Cannot convert to type from f64");
+    let middle = start + half_width;
+    let center = integrand(middle);
+    let mut kronrod = center * T::from(KRONROD_WEIGHTS[7]).expect("Cannot convert to type from f64");
+    let mut gauss = center * T::from(GAUSS_WEIGHTS[3]).expect("Cannot convert to type from f64");
+    for index in 0 .. 7 {
+        let offset = half_width * T::from(KRONROD_NODES[index]).expect("Cannot convert to type from f64");
+        let pair = integrand(middle - offset) + integrand(middle + offset);
+        kronrod = kronrod + pair * T::from(KRONROD_WEIGHTS[index]).expect("Cannot convert to type from f64");
+        if index % 2 == 1 {
+            gauss = gauss + pair * T::from(GAUSS_WEIGHTS[index / 2]).expect("Cannot convert to type from f64");
+        }
+    }
+    (kronrod * half_width, ((kronrod - gauss) * half_width).abs())
+}
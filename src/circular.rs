@@ -0,0 +1,110 @@
+use alloc::{string::String, vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::{FitMode, FittingError};
+
+/// Represents a GCV spline fitted to circular data such as Euler angles or headings, which wrap around after a full
+/// turn (360 for degrees or 2π for radians). The data are unwrapped before fitting, so the spline and its derivatives
+/// are continuous across the wrap. Values can be evaluated either unwrapped or wrapped into [-`full_turn` / 2,
+/// `full_turn` / 2); derivatives are never wrapped.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CircularGcvSpline<T: Float> {
+    spline: GcvSpline<T>,
+    full_turn: T,
+}
+
+impl<T: Float> CircularGcvSpline<T> {
+
+    /// Fits a CircularGcvSpline from user-provided time (knots) and data vectors, the size of a full turn in the units
+    /// of the data, and a [`FitMode`] selecting how the smoothing parameter is chosen. Default values are used for
+    /// half-order and weights.
+    pub fn from_data(time: &[T], data: &[T], full_turn: T, mode: FitMode<T>) -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, data, &vec![T::from(1.)
+                                                         .expect("Cannot convert to type from f64"); time.len()],
+                                   3, full_turn, mode)
+    }
+
+    /// Fits a CircularGcvSpline from user-provided time (knots) and data vectors, a vector of weights, a half-order,
+    /// the size of a full turn in the units of the data, and a [`FitMode`] selecting how the smoothing parameter is
+    /// chosen. The data are unwrapped with [`unwrap_angles`], so consecutive samples must differ by less than half a
+    /// turn.
+    pub fn from_full_parameters(time: &[T], data: &[T], weights: &[T], half_order: usize, full_turn: T,
+                                mode: FitMode<T>) -> Result<Self, FittingError> {
+        if !(full_turn > T::from(0.).expect("Cannot convert to type from f64") && full_turn.is_finite()) {
+            return Err(FittingError::InvalidFullTurn(String::from("Full turn must be finite and positive")));
+        }
+        Ok(CircularGcvSpline {
+            spline: GcvSpline::from_full_parameters_and_mode(time, &unwrap_angles(data, full_turn), weights,
+                                                             half_order, mode)?,
+            full_turn,
+        })
+    }
+
+    /// Evaluates the unwrapped spline at a single point.
+    pub fn single_point(&self, point: T) -> T {
+        self.spline.single_point(point)
+    }
+
+    /// Evaluates the unwrapped spline at a set of points.
+    pub fn points(&self, points: &[T]) -> Vec<T> {
+        self.spline.points(points)
+    }
+
+    /// Evaluates the spline at a single point, wrapped into [-`full_turn` / 2, `full_turn` / 2).
+    pub fn wrapped_point(&self, point: T) -> T {
+        wrap_angle(self.spline.single_point(point), self.full_turn)
+    }
+
+    /// Evaluates the spline at a set of points, wrapped into [-`full_turn` / 2, `full_turn` / 2).
+    pub fn wrapped_points(&self, points: &[T]) -> Vec<T> {
+        self.spline.points(points).into_iter().map(|value| wrap_angle(value, self.full_turn)).collect()
+    }
+
+    /// Evaluates a derivative of a given order at a single point. An order of 0 gives the unwrapped value.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> T {
+        self.spline.point_derivative(point, derivative_order)
+    }
+
+    /// Evaluates a derivative of a given order at a set of points. An order of 0 gives the unwrapped values.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        self.spline.derivative(points, derivative_order)
+    }
+
+    /// Returns the size of a full turn in the units of the data.
+    pub fn full_turn(&self) -> T {
+        self.full_turn
+    }
+
+    /// Returns the GCV spline fitted to the unwrapped data.
+    pub fn spline(&self) -> &GcvSpline<T> {
+        &self.spline
+    }
+}
+
+/// Unwraps circular data by adding multiples of `full_turn` so that consecutive values differ by at most half a turn.
+/// NaN values are kept, and the values after them are unwrapped relative to the last value that is not NaN.
+pub fn unwrap_angles<T: Float>(data: &[T], full_turn: T) -> Vec<T> {
+    let mut previous: Option<(T, T)> = None;
+    data.iter().map(|value| {
+        if value.is_nan() {
+            return *value;
+        }
+        let unwrapped = match previous {
+            Some((original, unwrapped)) => unwrapped + wrap_angle(*value - original, full_turn),
+            None => *value,
+        };
+        previous = Some((*value, unwrapped));
+        unwrapped
+    }).collect()
+}
+
+/// Wraps a value into [-`full_turn` / 2, `full_turn` / 2).
+pub fn wrap_angle<T: Float>(value: T, full_turn: T) -> T {
+    let half_turn = full_turn / T::from(2.).expect("Cannot convert to type from f64");
+    let wrapped = (value + half_turn) % full_turn;
+    if wrapped < T::from(0.).expect("Cannot convert to type from f64") {
+        wrapped + half_turn
+    } else {
+        wrapped - half_turn
+    }
+}
@@ -0,0 +1,169 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use num_traits::Float;
+use crate::extrapolation::{extrapolate, extrapolation_anchor};
+use crate::spline::GcvSpline;
+use crate::woltring::bansol::solve_transposed_system;
+use crate::woltring::gcvspl::DesignMatrices;
+use crate::woltring::search::{find_knot_interval, guess_knot_interval};
+use crate::woltring::splder::evaluate_spline;
+use crate::woltring::support::{FitDiagnostics, FittingError};
+
+/// Pointwise band around a GCV spline or one of its derivatives.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfidenceBand<T: Float> {
+    /// Value of the spline (or derivative) at each point.
+    pub estimate: Vec<T>,
+    /// Standard error at each point.
+    pub standard_error: Vec<T>,
+    /// Lower bound of the band at each point.
+    pub lower: Vec<T>,
+    /// Upper bound of the band at each point.
+    pub upper: Vec<T>,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Computes the standard errors of a derivative of a given order of the fitted spline at a set of points, given
+    /// the weights the spline was fitted with. The spline is linear in the data, so its variance follows from the
+    /// influence of each data point and the estimated variance of the fit, assuming independent errors with variances
+    /// inversely proportional to the weights. Points outside the knots follow the extrapolation of the spline, as in
+    /// its evaluation methods. Interpolating splines have no estimate of the variance, so they return
+    /// [`FittingError::InvalidFitMode`], and weights that do not reproduce the degrees of freedom of the fit return
+    /// [`FittingError::MissingFitData`].
+    pub fn standard_errors(&self, weights: &[T], points: &[T], derivative_order: usize)
+        -> Result<Vec<T>, FittingError> {
+        let diagnostics = self.diagnostics.ok_or_else(|| FittingError::MissingFitData(
+            String::from("Standard errors need a spline fitted to data")
+        ))?;
+        if diagnostics.smoothing_ratio <= T::from(0.).expect("Cannot convert to type from f64")
+            || !diagnostics.estimated_variance.is_finite() {
+            return Err(FittingError::InvalidFitMode(
+                String::from("Standard errors need a smoothing fit with a finite estimate of the variance")
+            ));
+        }
+        let num_knots = self.knots.len();
+        let (design, _) = self.fitted_design(weights, &diagnostics)?;
+        let decomposed = design.decomposed_system(diagnostics.smoothing_parameter)?;
+        let (first, last) = (self.knots[0], self.knots[num_knots - 1]);
+
+        let mut unit = vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots];
+        let mut basis = vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots];
+        let mut influence = vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots];
+        let mut errors = Vec::with_capacity(points.len());
+        for point in points {
+            // Only the B-splines around the knot interval where the polynomial pieces are evaluated are non-zero
+            let anchor = extrapolation_anchor(self.extrapolation, first, last, *point);
+            let knot_interval = find_knot_interval(&self.knots, anchor, guess_knot_interval(&self.knots, anchor));
+            basis.fill(T::from(0.).expect("Cannot convert to type from f64"));
+            for index in knot_interval.saturating_sub(self.half_order) .. (knot_interval + self.half_order)
+                .min(num_knots) {
+                unit[index] = T::from(1.).expect("Cannot convert to type from f64");
+                let evaluate = |point, derivative_order| evaluate_spline(derivative_order, self.half_order, point,
+                                                                         &self.knots, &unit, knot_interval);
+                basis[index] = extrapolate(self.extrapolation, first, last, *point, derivative_order, evaluate)
+                    .unwrap_or_else(|| evaluate(*point, derivative_order));
+                unit[index] = T::from(0.).expect("Cannot convert to type from f64");
+            }
+
+            // The influence of each data point on the evaluated value solves the transposed fitting system
+            solve_transposed_system(&decomposed, &basis, self.half_order, &mut influence)?;
+            let variance = influence.iter().zip(weights)
+                .fold(T::from(0.).expect("Cannot convert to type from f64"),
+                      |sum, (influence, weight)| sum + *influence * *influence / *weight);
+            errors.push((variance * diagnostics.estimated_variance).sqrt());
+        }
+        Ok(errors)
+    }
+
+    /// Computes a pointwise confidence band of the fitted spline at a set of points, given the weights the spline was
+    /// fitted with. `level` is the coverage probability of the band, such as 0.95, using a normal approximation.
+    pub fn confidence_band(&self, weights: &[T], points: &[T], level: T) -> Result<ConfidenceBand<T>, FittingError> {
+        self.derivative_confidence_band(weights, points, 0, level)
+    }
+
+    /// Computes a pointwise confidence band of a derivative of a given order of the fitted spline at a set of points,
+    /// given the weights the spline was fitted with. `level` is the coverage probability of the band, such as 0.95,
+    /// using a normal approximation.
+    pub fn derivative_confidence_band(&self, weights: &[T], points: &[T], derivative_order: usize, level: T)
+        -> Result<ConfidenceBand<T>, FittingError> {
+        let quantile = band_quantile(level)?;
+        let standard_error = self.standard_errors(weights, points, derivative_order)?;
+        Ok(build_band(self.derivative(points, derivative_order), standard_error, quantile))
+    }
+
+    /// Computes a pointwise prediction band for new unit-weight measurements at a set of points, given the weights the
+    /// spline was fitted with, which accounts for the measurement error as well as the uncertainty of the fitted
+    /// spline. `level` is the coverage probability of the band, such as 0.95, using a normal approximation.
+    pub fn prediction_band(&self, weights: &[T], points: &[T], level: T) -> Result<ConfidenceBand<T>, FittingError> {
+        let quantile = band_quantile(level)?;
+        let variance = self.diagnostics.map_or(T::from(0.).expect("Cannot convert to type from f64"),
+                                               |diagnostics| diagnostics.estimated_variance);
+        let standard_error = self.standard_errors(weights, points, 0)?.iter()
+            .map(|error| (*error * *error + variance).sqrt())
+            .collect();
+        Ok(build_band(self.points(points), standard_error, quantile))
+    }
+
+    /// Returns the design matrices and the leverage of each data point for the weights the spline was fitted with. The
+    /// weights are checked against the degrees of freedom of the fit, TRACE[I - influence matrix], which depend on the
+    /// weights.
+    pub(crate) fn fitted_design(&self, weights: &[T], diagnostics: &FitDiagnostics<T>)
+        -> Result<(DesignMatrices<T>, Vec<T>), FittingError> {
+        let design = DesignMatrices::new(&self.knots, weights, self.half_order)?;
+        let (leverage, degrees_of_freedom) = design.leverage(diagnostics.smoothing_parameter)?;
+        let mismatch = (degrees_of_freedom - diagnostics.degrees_of_freedom).abs();
+        let tolerance = T::epsilon().sqrt()
+            * degrees_of_freedom.max(T::from(1.).expect("Cannot convert to type from f64"));
+        if !(mismatch <= tolerance && mismatch.is_finite()) {
+            return Err(FittingError::MissingFitData(
+                String::from("Weights do not reproduce the degrees of freedom of the fit")
+            ));
+        }
+        Ok((design, leverage))
+    }
+}
+
+fn build_band<T: Float>(estimate: Vec<T>, standard_error: Vec<T>, quantile: T) -> ConfidenceBand<T> {
+    ConfidenceBand {
+        lower: estimate.iter().zip(&standard_error).map(|(value, error)| *value - quantile * *error).collect(),
+        upper: estimate.iter().zip(&standard_error).map(|(value, error)| *value + quantile * *error).collect(),
+        estimate,
+        standard_error,
+    }
+}
+
+/// Returns the two-sided standard normal quantile for a coverage probability.
+fn band_quantile<T: Float>(level: T) -> Result<T, FittingError> {
+    let level = level.to_f64().expect("Cannot convert from type to f64");
+    if !(level > 0. && level < 1.) {
+        return Err(FittingError::InvalidLevel(format!("Level {} must lie strictly between 0 and 1", level)));
+    }
+    Ok(T::from(normal_quantile(0.5 + level / 2.)).expect("Cannot convert to type from f64"))
+}
+
+/// Inverse of the standard normal cumulative distribution function, using the rational approximation of P. J. Acklam
+/// with a relative error below 1.2e-9.
+fn normal_quantile(probability: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e+01, 2.209460984245205e+02, -2.759285104469687e+02,
+                         1.38357751867269e+02, -3.066479806614716e+01, 2.506628277459239e+00];
+    const B: [f64; 5] = [-5.447609879822406e+01, 1.615858368580409e+02, -1.556989798598866e+02,
+                         6.680131188771972e+01, -1.328068155288572e+01];
+    const C: [f64; 6] = [-7.784894002430293e-03, -3.223964580411365e-01, -2.400758277161838e+00,
+                         -2.549732539343734e+00, 4.374664141464968e+00, 2.938163982698783e+00];
+    const D: [f64; 4] = [7.784695709041462e-03, 3.224671290700398e-01, 2.445134137142996e+00,
+                         3.754408661907416e+00];
+    let lower_tail = 0.02425;
+
+    if probability < lower_tail {
+        let q = (-2. * probability.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    } else if probability <= 1. - lower_tail {
+        let q = probability - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        -normal_quantile(1. - probability)
+    }
+}
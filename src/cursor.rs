@@ -0,0 +1,76 @@
+use alloc::{vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::search::walk_knot_interval;
+use crate::woltring::splder::{evaluate_spline, evaluate_spline_derivatives};
+
+/// Evaluates a GCV spline at a stream of points, remembering the knot interval of the previous point. Finding the
+/// interval of the next point takes time proportional to the number of knots between the two points, so evaluating
+/// points in increasing (or decreasing) order costs O(*n* + *m*) for *n* knots and *m* points, regardless of how the
+/// knots are spaced.
+#[derive(Clone, Debug)]
+pub struct SplineCursor<'a, T: Float> {
+    spline: &'a GcvSpline<T>,
+    knot_interval: usize,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Returns a cursor evaluating the GCV spline, starting at the first knot.
+    pub fn cursor(&self) -> SplineCursor<'_, T> {
+        SplineCursor {
+            spline: self,
+            knot_interval: 0,
+        }
+    }
+
+    /// Evaluates a GCV spline at a set of points sorted in increasing order. Unsorted points give the same values,
+    /// but may take longer to evaluate than with [`GcvSpline::points`].
+    pub fn sorted_points(&self, points: &[T]) -> Vec<T> {
+        self.sorted_derivative(points, 0)
+    }
+
+    /// Evaluates a derivative of a given order at a set of points sorted in increasing order. Unsorted points give the
+    /// same values, but may take longer to evaluate than with [`GcvSpline::derivative`].
+    pub fn sorted_derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        let mut cursor = self.cursor();
+        points.iter().map(|point| cursor.point_derivative(*point, derivative_order)).collect()
+    }
+}
+
+impl<'a, T: Float> SplineCursor<'a, T> {
+
+    /// Evaluates the spline at a single point.
+    pub fn single_point(&mut self, point: T) -> T {
+        self.point_derivative(point, 0)
+    }
+
+    /// Evaluates a derivative of a given order at a single point. Points outside the knots are handled according to
+    /// the [`Extrapolation`](crate::Extrapolation) of the spline.
+    pub fn point_derivative(&mut self, point: T, derivative_order: usize) -> T {
+        if let Some(value) = self.spline.extrapolated_derivative(point, derivative_order) {
+            return value;
+        }
+        self.knot_interval = walk_knot_interval(&self.spline.knots, point, self.knot_interval);
+        evaluate_spline(derivative_order, self.spline.half_order, point, &self.spline.knots, &self.spline.coefficients,
+                        self.knot_interval)
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` at a single point, in increasing order of
+    /// derivative.
+    pub fn evaluate_all_derivatives(&mut self, point: T, max_order: usize) -> Vec<T> {
+        if self.spline.extrapolated_derivative(point, 0).is_some() {
+            return self.spline.evaluate_all_derivatives(point, max_order);
+        }
+        self.knot_interval = walk_knot_interval(&self.spline.knots, point, self.knot_interval);
+        let mut values = vec![T::from(0.).expect("Cannot convert to type from f64"); max_order + 1];
+        evaluate_spline_derivatives(self.spline.half_order, point, &self.spline.knots, &self.spline.coefficients,
+                                    self.knot_interval, &mut values);
+        values
+    }
+
+    /// Returns the spline evaluated by the cursor.
+    pub fn spline(&self) -> &'a GcvSpline<T> {
+        self.spline
+    }
+}
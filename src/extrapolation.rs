@@ -0,0 +1,147 @@
+use alloc::{format, string::String, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::FittingError;
+
+/// Selects how a GCV spline and its derivatives are evaluated outside the range of its knots. This applies to the
+/// evaluation methods of [`GcvSpline`] and its [`Antiderivative`](crate::Antiderivative), and to its standard errors
+/// and bands; integrals and other analyses of the spline use its polynomial pieces.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Extrapolation {
+    /// Continues the polynomial pieces at either end of the spline, as in Woltring's GCVSPL.
+    #[default]
+    Polynomial,
+    /// Holds the value at the nearest end of the spline. Derivatives are 0.
+    Constant,
+    /// Continues the spline with the value and slope at the nearest end. Derivatives of order 2 and higher are 0.
+    Linear,
+    /// Evaluates to NaN.
+    Nan,
+    /// Repeats the spline with a period equal to the duration between its first and last knot.
+    Periodic,
+    /// Refuses the evaluation. Checked evaluation methods return [`FittingError::PointOutOfRange`], while the other
+    /// evaluation methods return NaN.
+    Error,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Returns the GCV spline with the provided extrapolation outside the range of its knots.
+    pub fn with_extrapolation(mut self, extrapolation: Extrapolation) -> Self {
+        self.extrapolation = extrapolation;
+        self
+    }
+
+    /// Returns how the GCV spline is evaluated outside the range of its knots.
+    pub fn extrapolation(&self) -> Extrapolation {
+        self.extrapolation
+    }
+
+    /// Evaluates a GCV spline at a single point, returning [`FittingError::PointOutOfRange`] if the point is not
+    /// finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_single_point(&self, point: T) -> Result<T, FittingError> {
+        self.try_point_derivative(point, 0)
+    }
+
+    /// Evaluates a GCV spline at a set of points, returning [`FittingError::PointOutOfRange`] if any point is not
+    /// finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_points(&self, points: &[T]) -> Result<Vec<T>, FittingError> {
+        self.try_derivative(points, 0)
+    }
+
+    /// Evaluates a derivative of a given order at a single point, returning [`FittingError::PointOutOfRange`] if the
+    /// point is not finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_point_derivative(&self, point: T, derivative_order: usize) -> Result<T, FittingError> {
+        self.check_point(point)?;
+        Ok(self.point_derivative(point, derivative_order))
+    }
+
+    /// Evaluates a derivative of a given order at a set of points, returning [`FittingError::PointOutOfRange`] if any
+    /// point is not finite or lies outside the knots with [`Extrapolation::Error`].
+    pub fn try_derivative(&self, points: &[T], derivative_order: usize) -> Result<Vec<T>, FittingError> {
+        points.iter().map(|point| self.try_point_derivative(*point, derivative_order)).collect()
+    }
+
+    /// Returns [`FittingError::PointOutOfRange`] if a point is not finite or lies outside the knots with
+    /// [`Extrapolation::Error`].
+    pub(crate) fn check_point(&self, point: T) -> Result<(), FittingError> {
+        if !point.is_finite() {
+            return Err(FittingError::PointOutOfRange(String::from("Evaluation points must be finite")));
+        }
+        if self.extrapolation == Extrapolation::Error && self.outside_knots(point) {
+            return Err(FittingError::PointOutOfRange(format!(
+                "Point {} lies outside the knots, from {} to {}",
+                point.to_f64().expect("Cannot convert from type to f64"),
+                self.knots[0].to_f64().expect("Cannot convert from type to f64"),
+                self.knots[self.knots.len() - 1].to_f64().expect("Cannot convert from type to f64")
+            )));
+        }
+        Ok(())
+    }
+
+    /// Evaluates a derivative of a given order at a point outside the knots according to the extrapolation of the
+    /// spline. Returns NaN for NaN points, and `None` for points within the knots and for
+    /// [`Extrapolation::Polynomial`], which evaluate the polynomial pieces directly.
+    pub(crate) fn extrapolated_derivative(&self, point: T, derivative_order: usize) -> Option<T> {
+        extrapolate(self.extrapolation, self.knots[0], self.knots[self.knots.len() - 1], point, derivative_order,
+                    |point, derivative_order| self.evaluate_piecewise(point, derivative_order))
+    }
+
+    fn outside_knots(&self, point: T) -> bool {
+        point < self.knots[0] || point > self.knots[self.knots.len() - 1]
+    }
+}
+
+/// Evaluates a derivative of a given order at a point outside the range from `first` to `last`, using `evaluate` to
+/// evaluate the polynomial pieces. Returns NaN for NaN points with every extrapolation, and `None` for points within
+/// the range and for [`Extrapolation::Polynomial`].
+pub(crate) fn extrapolate<T: Float, F: Fn(T, usize) -> T>(extrapolation: Extrapolation, first: T, last: T, point: T,
+                                                        derivative_order: usize, evaluate: F) -> Option<T> {
+    // NaN lies in no knot interval, so it is never passed on to the polynomial pieces
+    if point.is_nan() {
+        return Some(T::nan());
+    }
+    if !(point < first || point > last) {
+        return None;
+    }
+    let anchor = extrapolation_anchor(extrapolation, first, last, point);
+
+    match extrapolation {
+        Extrapolation::Polynomial => None,
+        Extrapolation::Constant => Some(match derivative_order {
+            0 => evaluate(anchor, 0),
+            _ => T::from(0.).expect("Cannot convert to type from f64"),
+        }),
+        Extrapolation::Linear => Some(match derivative_order {
+            0 => evaluate(anchor, 0) + evaluate(anchor, 1) * (point - anchor),
+            1 => evaluate(anchor, 1),
+            _ => T::from(0.).expect("Cannot convert to type from f64"),
+        }),
+        Extrapolation::Nan | Extrapolation::Error => Some(T::nan()),
+        Extrapolation::Periodic => Some(evaluate(anchor, derivative_order)),
+    }
+}
+
+/// Returns the point at which `extrapolate` evaluates the polynomial pieces for a point outside the range from `first`
+/// to `last`: the nearest end of the range for [`Extrapolation::Constant`] and [`Extrapolation::Linear`], and the point
+/// wrapped into the range for [`Extrapolation::Periodic`]. Other points are returned unchanged.
+pub(crate) fn extrapolation_anchor<T: Float>(extrapolation: Extrapolation, first: T, last: T, point: T) -> T {
+    if !(point < first || point > last) {
+        return point;
+    }
+    match extrapolation {
+        Extrapolation::Constant | Extrapolation::Linear if point < first => first,
+        Extrapolation::Constant | Extrapolation::Linear => last,
+        Extrapolation::Periodic => {
+            let period = last - first;
+            let wrapped = first + (point - first) % period;
+            if wrapped < first {
+                wrapped + period
+            } else {
+                wrapped
+            }
+        }
+        _ => point,
+    }
+}
@@ -0,0 +1,66 @@
+use alloc::{vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::gcvspl::DesignMatrices;
+use crate::woltring::support::{check_vector_length, FitDiagnostics, FitMode, FittingError};
+
+/// Fits GCV splines to any number of data vectors sampled at the same time points. The B-spline basis and weighted
+/// matrices are computed once when the fitter is created, and its working storage is reused by every fit.
+#[derive(Clone, Debug)]
+pub struct GcvFitter<T: Float> {
+    knots: Vec<T>,
+    design: DesignMatrices<T>,
+    band_matrix: Vec<T>,
+    stats: [T; 6],
+}
+
+impl<T: Float> GcvFitter<T> {
+
+    /// Creates a GcvFitter for a user-provided time (knots) vector. Default values are used for half-order and
+    /// weights.
+    pub fn from_time(time: &[T]) -> Result<Self, FittingError> {
+        Self::new(time, &vec![T::from(1.).expect("Cannot convert to type from f64"); time.len()], 3)
+    }
+
+    /// Creates a GcvFitter for a user-provided time (knots) vector, a vector of weights, and a half-order.
+    pub fn new(time: &[T], weights: &[T], half_order: usize) -> Result<Self, FittingError> {
+        let design = DesignMatrices::new(time, weights, half_order)?;
+        Ok(GcvFitter {
+            knots: time.to_vec(),
+            band_matrix: vec![T::from(0.).expect("Cannot convert to type from f64"); design.band_matrix_len()],
+            design,
+            stats: [T::from(0.).expect("Cannot convert to type from f64"); 6],
+        })
+    }
+
+    /// Fits a GcvSpline to a data vector, choosing the smoothing parameter according to `mode`.
+    pub fn fit(&mut self, data: &[T], mode: FitMode<T>) -> Result<GcvSpline<T>, FittingError> {
+        let mut coefficients = vec![T::from(0.).expect("Cannot convert to type from f64"); self.knots.len()];
+        let diagnostics = self.fit_coefficients(data, mode, &mut coefficients)?;
+        Ok(GcvSpline::from_fitted_parts(self.knots.clone(), coefficients, self.design.half_order(), diagnostics))
+    }
+
+    /// Fits a data vector, choosing the smoothing parameter according to `mode`, and writes the spline coefficients
+    /// into `coefficients`. This does not allocate.
+    pub fn fit_coefficients(&mut self, data: &[T], mode: FitMode<T>, coefficients: &mut [T])
+        -> Result<FitDiagnostics<T>, FittingError> {
+        check_vector_length(data, self.knots.len())?;
+        check_vector_length(coefficients, self.knots.len())?;
+        self.design.fit_into(data, mode, coefficients, &mut self.band_matrix, &mut self.stats)
+    }
+
+    /// Returns the half-order of the splines produced by the fitter.
+    pub fn half_order(&self) -> usize {
+        self.design.half_order()
+    }
+
+    /// Returns a copy of the time vector the fitter was created with.
+    pub fn time(&self) -> Vec<T> {
+        self.knots.clone()
+    }
+
+    /// Returns a copy of the knots vector the fitter was created with.
+    pub fn knots(&self) -> Vec<T> {
+        self.time()
+    }
+}
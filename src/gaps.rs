@@ -0,0 +1,100 @@
+use alloc::{format, vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::{check_vector_length, FitMode, FittingError};
+
+/// Describes a run of consecutive missing samples.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gap {
+    /// Index of the first missing sample.
+    pub start: usize,
+    /// Number of consecutive missing samples.
+    pub length: usize,
+}
+
+/// Represents a GCV spline fitted through missing samples, along with the data evaluated on the full time grid.
+/// Samples are missing when their value is NaN or their weight is 0. Missing samples are left out of the fit and
+/// filled in by evaluating the spline; missing samples at either end of the data are extrapolated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GapFill<T: Float> {
+    spline: GcvSpline<T>,
+    values: Vec<T>,
+    filled_indices: Vec<usize>,
+    gaps: Vec<Gap>,
+}
+
+impl<T: Float> GapFill<T> {
+
+    /// Fits a GCV spline through the available samples of user-provided time (knots) and data vectors. Default values
+    /// are used for half-order and weights. Fitting is refused with [`FittingError::GapTooLong`] if more than
+    /// `max_gap` consecutive samples are missing.
+    pub fn from_data(time: &[T], data: &[T], mode: FitMode<T>, max_gap: usize)
+        -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, data, &vec![T::from(1.)
+                                                         .expect("Cannot convert to type from f64"); time.len()],
+                                   3, mode, max_gap)
+    }
+
+    /// Fits a GCV spline through the available samples of user-provided time (knots) and data vectors, a vector of
+    /// weights, and a half-order. Fitting is refused with [`FittingError::GapTooLong`] if more than `max_gap`
+    /// consecutive samples are missing.
+    pub fn from_full_parameters(time: &[T], data: &[T], weights: &[T], half_order: usize,
+                                mode: FitMode<T>, max_gap: usize) -> Result<Self, FittingError> {
+        check_vector_length(data, time.len())?;
+        check_vector_length(weights, time.len())?;
+
+        let missing: Vec<bool> = data.iter().zip(weights)
+            .map(|(value, weight)| value.is_nan() || *weight == T::from(0.).expect("Cannot convert to type from f64"))
+            .collect();
+        let gaps = find_gaps(&missing);
+        if let Some(gap) = gaps.iter().find(|gap| gap.length > max_gap) {
+            return Err(FittingError::GapTooLong(format!(
+                "Gap of {} samples starting at index {} exceeds the maximum of {}", gap.length, gap.start, max_gap
+            )));
+        }
+
+        let kept = |vector: &[T]| -> Vec<T> {
+            vector.iter().zip(&missing).filter(|(_, missing)| !**missing).map(|(value, _)| *value).collect()
+        };
+        let spline = GcvSpline::from_full_parameters_and_mode(&kept(time), &kept(data), &kept(weights),
+                                                              half_order, mode)?;
+
+        Ok(GapFill {
+            values: spline.points(time),
+            filled_indices: (0..time.len()).filter(|index| missing[*index]).collect(),
+            gaps,
+            spline,
+        })
+    }
+
+    /// Returns the GCV spline fitted through the available samples.
+    pub fn spline(&self) -> &GcvSpline<T> {
+        &self.spline
+    }
+
+    /// Returns the spline evaluated at every point of the original time vector.
+    pub fn values(&self) -> Vec<T> {
+        self.values.clone()
+    }
+
+    /// Returns the indices of the samples that were missing and have been filled.
+    pub fn filled_indices(&self) -> Vec<usize> {
+        self.filled_indices.clone()
+    }
+
+    /// Returns the runs of missing samples, in order of appearance.
+    pub fn gaps(&self) -> Vec<Gap> {
+        self.gaps.clone()
+    }
+}
+
+fn find_gaps(missing: &[bool]) -> Vec<Gap> {
+    let mut gaps: Vec<Gap> = Vec::new();
+    for (index, _) in missing.iter().enumerate().filter(|(_, missing)| **missing) {
+        match gaps.last_mut() {
+            Some(gap) if gap.start + gap.length == index => gap.length += 1,
+            _ => gaps.push(Gap { start: index, length: 1 }),
+        }
+    }
+    gaps
+}
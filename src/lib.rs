@@ -27,24 +27,73 @@
 //! // Second derivative should be close to 2.
 //! assert!((spline.point_derivative(2., 2) - 2.).abs() < 1e-12);
 //! ```
+//!
+//! # Features
+//! - `std` (default): Links the standard library. Without it, the crate is `no_std` and only needs `alloc`.
+//!   `FittingError` implements `core::error::Error`, which is `std::error::Error`, either way.
+//! - `serde`: Serializes and deserializes `GcvSpline` and `FitDiagnostics` with a versioned schema. Loaded splines
+//!   are validated like fitted ones.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
+pub mod calculus;
+pub mod circular;
+pub mod confidence;
+pub mod cursor;
+pub mod extrapolation;
+pub mod fitter;
+pub mod gaps;
+pub mod parametric;
+pub mod periodic;
+pub mod piecewise;
+pub mod resample;
+pub mod residuals;
+pub mod roots;
+pub mod rotation;
+pub mod robust;
+#[cfg(feature = "serde")]
+pub mod serialization;
 pub mod spline;
+pub mod spline_set;
 pub mod woltring;
+pub mod workspace;
+pub use crate::calculus::Antiderivative;
+pub use crate::circular::{unwrap_angles, wrap_angle, CircularGcvSpline};
+pub use crate::confidence::ConfidenceBand;
+pub use crate::cursor::SplineCursor;
+pub use crate::extrapolation::Extrapolation;
+pub use crate::fitter::GcvFitter;
+pub use crate::gaps::{Gap, GapFill};
+pub use crate::parametric::ParametricGcvSpline;
+pub use crate::periodic::PeriodicGcvSpline;
+pub use crate::piecewise::PiecewisePolynomial;
+pub use crate::resample::Resampled;
+pub use crate::residuals::ResidualDiagnostics;
+pub use crate::roots::{Extremum, ExtremumKind};
+pub use crate::rotation::RotationGcvSpline;
+pub use crate::robust::{RobustFit, RobustLoss, RobustOptions};
 pub use crate::spline::GcvSpline;
-pub use crate::woltring::support::FittingError;
+pub use crate::spline_set::{ChannelSmoothing, GcvSplineSet};
+pub use crate::woltring::support::{FitDiagnostics, FitMode, FittingError};
+pub use crate::workspace::{evaluate_with_workspace, fit_with_workspace, required_evaluation_workspace,
+                           required_workspace};
 
 #[cfg(test)]
 mod tests {
     use crate::woltring::{gcvspl::fit_gcv_spline, splder::evaluate_spline};
-    use crate::GcvSpline;
+    use crate::{evaluate_with_workspace, fit_with_workspace, required_evaluation_workspace, required_workspace,
+                unwrap_angles, wrap_angle, ChannelSmoothing, CircularGcvSpline, Extrapolation, ExtremumKind, FitMode,
+                FittingError, Gap, GapFill, GcvFitter, GcvSpline, GcvSplineSet, ParametricGcvSpline, PeriodicGcvSpline,
+                RobustFit, RobustLoss, RobustOptions, RotationGcvSpline};
 
     #[test]
     fn test_sin_eval() {
         let knots: Vec<f64> = (0..=100).map(|e| e as f64).collect();
-        let data = knots.iter().map(|e| (e * 0.01).sin()).collect();
+        let data: Vec<f64> = knots.iter().map(|e| (e * 0.01).sin()).collect();
         let weights = vec![1.0; knots.len()];
 
-        let coefs = fit_gcv_spline(&knots, &data, &weights, 3, 0.0).unwrap();
+        let (coefs, _) = fit_gcv_spline(&knots, &data, &weights, 3, FitMode::Interpolate).unwrap();
         let value = evaluate_spline(0, 3, 50.5, &knots, &coefs, 0);
         assert!((value - 0.505_f64.sin()).abs() < 1e-15)
     }
@@ -52,10 +101,10 @@ mod tests {
     #[test]
     fn test_sin_derivative() {
         let knots: Vec<f64> = (0..=100).map(|e| e as f64).collect();
-        let data = knots.iter().map(|e| (e * 0.01).sin()).collect();
+        let data: Vec<f64> = knots.iter().map(|e| (e * 0.01).sin()).collect();
         let weights = vec![1.0; knots.len()];
 
-        let coefs = fit_gcv_spline(&knots, &data, &weights, 3, 0.0).unwrap();
+        let (coefs, _) = fit_gcv_spline(&knots, &data, &weights, 3, FitMode::Interpolate).unwrap();
         let value = evaluate_spline(1, 3, 50.5, &knots, &coefs, 0);
         assert!((value - 0.01 * 0.505_f64.cos()).abs() < 1e-15)
     }
@@ -63,10 +112,10 @@ mod tests {
     #[test]
     fn test_sin_second_derivative() {
         let knots: Vec<f64> = (0..=100).map(|e| e as f64).collect();
-        let data = knots.iter().map(|e| (e * 0.01).sin()).collect();
+        let data: Vec<f64> = knots.iter().map(|e| (e * 0.01).sin()).collect();
         let weights = vec![1.0; knots.len()];
 
-        let coefs = fit_gcv_spline(&knots, &data, &weights, 3, 0.0).unwrap();
+        let (coefs, _) = fit_gcv_spline(&knots, &data, &weights, 3, FitMode::Interpolate).unwrap();
         let value = evaluate_spline(2, 3, 50.5, &knots, &coefs, 0);
         assert!((value + 0.01 * 0.01 * 0.505_f64.sin()).abs() < 1e-15)
     }
@@ -83,8 +132,8 @@ mod tests {
         let values = vec![0., 1., 9., 16., 25., 36.];
 
         let spline = GcvSpline::from_data(&time, &values).unwrap();
-        let interpolated = spline.points(&vec![2., 3.5, 5.]);
-        let derivatives = spline.derivative(&vec![2., 3.5, 5.], 1);
+        let interpolated = spline.points(&[2., 3.5, 5.]);
+        let derivatives = spline.derivative(&[2., 3.5, 5.], 1);
         assert!((interpolated[0] - 2. * 2.).abs() < 1e-12);
         assert!((interpolated[1] - 3.5 * 3.5).abs() < 1e-12);
         assert!((interpolated[2] - 5. * 5.).abs() < 1e-12);
@@ -92,4 +141,720 @@ mod tests {
         assert!((derivatives[1] - 2. * 3.5).abs() < 1e-12);
         assert!((derivatives[2] - 2. * 5.).abs() < 1e-12);
     }
+
+    /// Samples sin(`frequency` * t) at 50 time points 0.02 apart, with alternating noise of +/-0.05.
+    fn noisy_sine(frequency: f64) -> (Vec<f64>, Vec<f64>) {
+        let time: Vec<f64> = (0..50).map(|e| e as f64 * 0.02).collect();
+        let values = time.iter().enumerate()
+            .map(|(i, t)| (frequency * t).sin() + if i % 2 == 0 { 0.05 } else { -0.05 }).collect();
+        (time, values)
+    }
+
+    #[test]
+    fn test_gcv_mode_smooths_noise() {
+        let (time, values) = noisy_sine(6.);
+
+        let spline = GcvSpline::from_data_and_mode(&time, &values, FitMode::Gcv).unwrap();
+        for t in [0.25, 0.5, 0.75] {
+            assert!((spline.single_point(t) - (6. * t).sin()).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_degrees_of_freedom_match_influence_trace() {
+        // The smoothing parameter chosen for prescribed degrees of freedom does not depend on the data, so the
+        // influence matrix can be built column by column by fitting unit vectors
+        let time: Vec<f64> = (0..20).map(|e| (e as f64 * 0.3).exp()).collect();
+        let weights: Vec<f64> = (0..20).map(|e| 1. + (e % 3) as f64).collect();
+        let mut influence_trace = 0.;
+        for index in 0..time.len() {
+            let mut unit = vec![0.; time.len()];
+            unit[index] = 1.;
+            let spline = GcvSpline::from_full_parameters_and_mode(&time, &unit, &weights, 2,
+                                                                  FitMode::DegreesOfFreedom(8.)).unwrap();
+            influence_trace += spline.single_point(time[index]);
+        }
+        assert!((time.len() as f64 - influence_trace - 8.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_invalid_fit_mode() {
+        let time: Vec<f64> = vec![0., 1., 3., 4., 5., 6.];
+        let values = vec![0., 1., 9., 16., 25., 36.];
+
+        assert!(GcvSpline::from_data_and_mode(&time, &values, FitMode::DegreesOfFreedom(4.)).is_err());
+        assert!(GcvSpline::from_data_and_mode(&time, &values, FitMode::SmoothingParameter(-1.)).is_err());
+    }
+
+    #[test]
+    fn test_fit_diagnostics() {
+        let (time, values) = noisy_sine(6.);
+
+        let spline = GcvSpline::from_data_and_mode(&time, &values, FitMode::DegreesOfFreedom(20.)).unwrap();
+        let diagnostics = spline.diagnostics().unwrap();
+        assert!((diagnostics.degrees_of_freedom - 20.).abs() < 1e-3);
+        assert!(diagnostics.evaluations > 1);
+
+        let refit = GcvSpline::from_data_and_mode(
+            &time, &values, FitMode::SmoothingParameter(diagnostics.smoothing_parameter)).unwrap();
+        assert_eq!(refit.diagnostics().unwrap().evaluations, 1);
+        assert!((refit.diagnostics().unwrap().gcv - diagnostics.gcv).abs() < 1e-12);
+
+        // Smoothing parameters beyond a least-squares polynomial are bounded, and the bounded value reproduces the fit
+        let polynomial = GcvSpline::from_data_and_mode(&time, &values, FitMode::SmoothingParameter(1E30)).unwrap();
+        let bounded = polynomial.diagnostics().unwrap().smoothing_parameter;
+        assert!(bounded < 1E30);
+        let refit = GcvSpline::from_data_and_mode(&time, &values, FitMode::SmoothingParameter(bounded)).unwrap();
+        assert_eq!(refit.coefficients(), polynomial.coefficients());
+        assert!(GcvSpline::<f64>::new().diagnostics().is_none());
+    }
+
+    #[test]
+    fn test_spline_set_channels() {
+        let time = noisy_sine(1.).0;
+        let channels: Vec<Vec<f64>> = (1..=3).map(|c| noisy_sine(c as f64).1).collect();
+
+        let independent = GcvSplineSet::from_channels(&time, &channels, FitMode::Gcv,
+                                                       ChannelSmoothing::Independent).unwrap();
+        let single = GcvSpline::from_data_and_mode(&time, &channels[1], FitMode::Gcv).unwrap();
+        assert_eq!(independent.channel(1).unwrap(), single);
+        assert!((independent.single_point(0.5)[1] - single.single_point(0.5)).abs() < 1e-15);
+
+        let shared = GcvSplineSet::from_channels(&time, &channels, FitMode::Gcv,
+                                                 ChannelSmoothing::Shared).unwrap();
+        let diagnostics = shared.diagnostics();
+        assert_eq!(shared.num_channels(), 3);
+        assert!(diagnostics.iter().all(|d| d.smoothing_parameter == diagnostics[0].smoothing_parameter));
+        let derivatives = shared.derivative(&[0.25, 0.5], 1);
+        assert_eq!(derivatives.len(), 3);
+        assert!((derivatives[2][1] - 3. * 1.5_f64.cos()).abs() < 0.2);
+        assert!(shared.single_point(f64::NAN).iter().all(|value| value.is_nan()));
+        let weights = vec![1.; time.len()];
+        assert!(shared.channel(2).unwrap().residual_diagnostics(&channels[2], &weights).is_ok());
+        let borrowed: Vec<&[f64]> = channels.iter().map(|channel| channel.as_slice()).collect();
+        assert_eq!(GcvSplineSet::from_channels(&time, &borrowed, FitMode::Gcv, ChannelSmoothing::Shared).unwrap(),
+                   shared);
+    }
+
+    #[test]
+    fn test_fitter_reuse() {
+        let time = noisy_sine(1.).0;
+        let mut fitter = GcvFitter::from_time(&time).unwrap();
+        let mut coefficients = vec![0.; time.len()];
+
+        for frequency in [1., 3., 6.] {
+            let values = noisy_sine(frequency).1;
+            let expected = GcvSpline::from_data_and_mode(&time, &values, FitMode::Gcv).unwrap();
+            let diagnostics = fitter.fit_coefficients(&values, FitMode::Gcv, &mut coefficients).unwrap();
+            assert_eq!(fitter.fit(&values, FitMode::Gcv).unwrap(), expected);
+            assert_eq!(Some(diagnostics), expected.diagnostics());
+        }
+        assert!(fitter.fit_coefficients(&[0.; 10], FitMode::Gcv, &mut coefficients).is_err());
+    }
+
+    #[test]
+    fn test_gap_fill() {
+        let time: Vec<f64> = (0..20).map(|e| e as f64).collect();
+        let mut values: Vec<f64> = time.iter().map(|t| t * t).collect();
+        for index in [0, 5, 6, 7, 12] {
+            values[index] = f64::NAN;
+        }
+
+        let filled = GapFill::from_data(&time, &values, FitMode::Interpolate, 3).unwrap();
+        assert_eq!(filled.filled_indices(), vec![0, 5, 6, 7, 12]);
+        assert_eq!(filled.gaps(), vec![Gap { start: 0, length: 1 }, Gap { start: 5, length: 3 },
+                                       Gap { start: 12, length: 1 }]);
+        for index in [5, 6, 7, 12] {
+            assert!((filled.values()[index] - time[index] * time[index]).abs() < 1e-9);
+        }
+        assert!(GapFill::from_data(&time, &values, FitMode::Interpolate, 2).is_err());
+    }
+
+    #[test]
+    fn test_standard_errors_match_influence() {
+        let time: Vec<f64> = (0..30).map(|e| e as f64 * 0.1).collect();
+        let values: Vec<f64> = time.iter().enumerate()
+            .map(|(i, t)| t.sin() + if i % 3 == 0 { 0.1 } else { -0.05 }).collect();
+        let mode = FitMode::SmoothingParameter(1e-4);
+        let spline = GcvSpline::from_data_and_mode(&time, &values, mode).unwrap();
+        let variance = spline.diagnostics().unwrap().estimated_variance;
+        let weights = vec![1.; time.len()];
+
+        // The influence of each data point equals the spline fitted to a unit impulse
+        let points = vec![0.55, 2.9];
+        for derivative_order in 0..=2 {
+            let errors = spline.standard_errors(&weights, &points, derivative_order).unwrap();
+            for (index, point) in points.iter().enumerate() {
+                let sum_of_squares: f64 = (0..time.len()).map(|impulse| {
+                    let mut unit = vec![0.; time.len()];
+                    unit[impulse] = 1.;
+                    let influence = GcvSpline::from_data_and_mode(&time, &unit, mode).unwrap()
+                        .point_derivative(*point, derivative_order);
+                    influence * influence
+                }).sum();
+                assert!(((sum_of_squares * variance).sqrt() - errors[index]).abs() < 1e-8 * (1. + errors[index]));
+            }
+        }
+
+        let band = spline.confidence_band(&weights, &points, 0.95).unwrap();
+        assert!((band.upper[0] - band.estimate[0] - 1.959964 * band.standard_error[0]).abs() < 1e-6);
+        assert!(spline.prediction_band(&weights, &points, 0.95).unwrap().standard_error[0] > band.standard_error[0]);
+        assert!(spline.confidence_band(&weights, &points, 1.5).is_err());
+        assert!(GcvSpline::<f64>::new().confidence_band(&weights, &points, 0.95).is_err());
+        let interpolating = GcvSpline::from_data(&time, &values).unwrap();
+        assert!(matches!(interpolating.standard_errors(&weights, &points, 0), Err(FittingError::InvalidFitMode(_))));
+
+        // Weights the spline was not fitted with are refused
+        assert!(matches!(spline.standard_errors(&weights[1..], &points, 0),
+                         Err(FittingError::VectorLengthMismatch(_))));
+        let other_weights: Vec<f64> = (0..time.len()).map(|i| 1. + (i % 2) as f64).collect();
+        assert!(matches!(spline.prediction_band(&other_weights, &points, 0.95), Err(FittingError::MissingFitData(_))));
+
+        // Outside the knots, the standard errors follow the extrapolation like the estimate
+        let last = time[time.len() - 1];
+        let constant = spline.clone().with_extrapolation(Extrapolation::Constant);
+        let errors = constant.standard_errors(&weights, &[last + 0.5, last], 0).unwrap();
+        assert!((errors[0] - errors[1]).abs() < 1e-12);
+        assert_eq!(constant.standard_errors(&weights, &[last + 0.5], 1).unwrap(), vec![0.]);
+        let linear = spline.with_extrapolation(Extrapolation::Linear);
+        let errors = linear.standard_errors(&weights, &[-0.5, 0.], 1).unwrap();
+        assert!((errors[0] - errors[1]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_residual_diagnostics() {
+        let time: Vec<f64> = (0..40).map(|e| e as f64 * 0.05).collect();
+        let mut values: Vec<f64> = time.iter().enumerate()
+            .map(|(i, t)| t.sin() + if i % 2 == 0 { 0.02 } else { -0.02 }).collect();
+        values[20] += 1.;
+
+        let spline = GcvSpline::from_data_and_mode(&time, &values, FitMode::Gcv).unwrap();
+        let weights = vec![1.; time.len()];
+        let residuals = spline.residual_diagnostics(&values, &weights).unwrap();
+        assert!(matches!(spline.residual_diagnostics(&values[1..], &weights),
+                         Err(FittingError::VectorLengthMismatch(_))));
+        let mut other_values = values.clone();
+        other_values[5] += 0.5;
+        assert!(matches!(spline.residual_diagnostics(&other_values, &weights), Err(FittingError::MissingFitData(_))));
+        other_values[5] = f64::NAN;
+        assert!(matches!(spline.residual_diagnostics(&other_values, &weights), Err(FittingError::MissingFitData(_))));
+        let other_weights: Vec<f64> = (0..time.len()).map(|i| 1. + (i % 2) as f64).collect();
+        assert!(matches!(spline.residual_diagnostics(&values, &other_weights), Err(FittingError::MissingFitData(_))));
+        let leverage_sum: f64 = residuals.leverage.iter().sum();
+        let dof = spline.diagnostics().unwrap().degrees_of_freedom;
+        assert!((leverage_sum - (time.len() as f64 - dof)).abs() < 1e-3);
+        assert!((residuals.residuals[3] - (values[3] - spline.single_point(time[3]))).abs() < 1e-15);
+
+        // The glitch dominates the fit
+        let most_influential = (0..time.len())
+            .max_by(|a, b| residuals.cooks_distance[*a].partial_cmp(&residuals.cooks_distance[*b]).unwrap());
+        assert_eq!(most_influential, Some(20));
+        assert!(residuals.standardized_residuals[20] > 3.);
+    }
+
+    #[test]
+    fn test_robust_fit_rejects_outlier() {
+        let time: Vec<f64> = (0..40).map(|e| e as f64 * 0.05).collect();
+        let mut values: Vec<f64> = time.iter().enumerate()
+            .map(|(i, t)| t.sin() + if i % 2 == 0 { 0.02 } else { -0.02 }).collect();
+        values[20] += 1.;
+
+        let plain = GcvSpline::from_data_and_mode(&time, &values, FitMode::Gcv).unwrap();
+        for loss in [RobustLoss::Bisquare(4.685), RobustLoss::Huber(1.345)] {
+            let options = RobustOptions { loss, ..RobustOptions::default() };
+            let robust = RobustFit::from_data(&time, &values, FitMode::Gcv, options).unwrap();
+            let error = (robust.spline().single_point(time[20]) - time[20].sin()).abs();
+            assert!(error < 0.05);
+            assert!(error < (plain.single_point(time[20]) - time[20].sin()).abs());
+            assert!(robust.robustness_weights()[20] < 0.2);
+            assert!(robust.iterations() <= options.max_iterations);
+        }
+        assert!(RobustFit::from_data(&time, &values, FitMode::Interpolate, RobustOptions::default()).is_err());
+        for mode in [FitMode::KnownVariance(0.), FitMode::SmoothingParameter(0.), FitMode::DegreesOfFreedom(0.)] {
+            assert!(matches!(RobustFit::from_data(&time, &values, mode, RobustOptions::default()),
+                             Err(FittingError::InvalidFitMode(_))));
+        }
+        let no_iterations = RobustOptions { max_iterations: 0, ..RobustOptions::default() };
+        assert!(matches!(RobustFit::from_data(&time, &values, FitMode::Gcv, no_iterations),
+                         Err(FittingError::InvalidFitMode(_))));
+        for loss in [RobustLoss::Huber(0.), RobustLoss::Huber(f64::INFINITY), RobustLoss::Bisquare(-1.),
+                     RobustLoss::Bisquare(f64::NAN)] {
+            let options = RobustOptions { loss, ..RobustOptions::default() };
+            assert!(matches!(RobustFit::from_data(&time, &values, FitMode::Gcv, options),
+                             Err(FittingError::InvalidFitMode(_))));
+        }
+        for tolerance in [-1e-4, f64::NAN] {
+            let options = RobustOptions { tolerance, ..RobustOptions::default() };
+            assert!(matches!(RobustFit::from_data(&time, &values, FitMode::Gcv, options),
+                             Err(FittingError::InvalidFitMode(_))));
+        }
+        let rejecting = RobustOptions { loss: RobustLoss::Bisquare(1e-9), ..RobustOptions::default() };
+        assert!(matches!(RobustFit::from_data(&time, &values, FitMode::Gcv, rejecting),
+                         Err(FittingError::InsufficientSamples(_))));
+    }
+
+    #[test]
+    fn test_integral_and_antiderivative() {
+        let time: Vec<f64> = (0..20).map(|e| e as f64 * 0.5).collect();
+        let values: Vec<f64> = time.iter().map(|t| t * t).collect();
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+
+        assert!((spline.integral(0., 3.) - 9.).abs() < 1e-10);
+        assert!((spline.integral(1.25, 7.8) - (7.8_f64.powi(3) - 1.25_f64.powi(3)) / 3.).abs() < 1e-9);
+        assert!((spline.integral(3., 0.) + 9.).abs() < 1e-10);
+
+        let antiderivative = spline.antiderivative();
+        assert!((antiderivative.single_point(5.) - 125. / 3.).abs() < 1e-9);
+        assert!((antiderivative.single_point(9.5) - 9.5_f64.powi(3) / 3.).abs() < 1e-9);
+        assert!((antiderivative.single_point(-0.5) + 0.5_f64.powi(3) / 3.).abs() < 1e-9);
+        assert!((antiderivative.single_point(10.5) - spline.integral(0., 10.5)).abs() < 1e-9);
+        assert!((antiderivative.point_derivative(2.2, 1) - 2.2 * 2.2).abs() < 1e-10);
+        assert!((antiderivative.point_derivative(2.2, 2) - 4.4).abs() < 1e-10);
+
+        // Outside the knots the antiderivative follows the extrapolation of the spline, consistently with its
+        // derivatives
+        let last = time[time.len() - 1];
+        let constant = spline.clone().with_extrapolation(Extrapolation::Constant).antiderivative();
+        assert_eq!(constant.single_point(last + 1.), constant.single_point(last));
+        assert_eq!(constant.point_derivative(last + 1., 1), 0.);
+        let linear = antiderivative.clone().with_extrapolation(Extrapolation::Linear);
+        let slope = (linear.single_point(-1.5) - linear.single_point(-0.5)) / -1.;
+        assert!((slope - linear.point_derivative(-1., 1)).abs() < 1e-10);
+        assert_eq!(linear.second_derivative(&[-1.]), vec![0.]);
+        let checked = antiderivative.with_extrapolation(Extrapolation::Error);
+        assert!((checked.try_single_point(5.).unwrap() - 125. / 3.).abs() < 1e-9);
+        assert!(matches!(checked.try_points(&[5., last + 1.]), Err(FittingError::PointOutOfRange(_))));
+    }
+
+    #[test]
+    fn test_roots_and_extrema() {
+        let time: Vec<f64> = (0..=60).map(|e| e as f64 * 0.1).collect();
+        let values: Vec<f64> = time.iter().map(|t| t.sin()).collect();
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+        let pi = std::f64::consts::PI;
+
+        let roots = spline.roots(0, 0.5);
+        assert_eq!(roots.len(), 2);
+        assert!((roots[0] - pi / 6.).abs() < 1e-4);
+        assert!((roots[1] - 5. * pi / 6.).abs() < 1e-4);
+        assert!((spline.single_point(roots[1]) - 0.5).abs() < 1e-12);
+
+        let extrema = spline.local_extrema();
+        assert_eq!(extrema.len(), 2);
+        assert_eq!(extrema[0].kind, ExtremumKind::Maximum);
+        assert!((extrema[0].time - pi / 2.).abs() < 1e-4);
+        assert!((extrema[0].value - 1.).abs() < 1e-4);
+        assert_eq!(extrema[1].kind, ExtremumKind::Minimum);
+        assert!((extrema[1].time - 1.5 * pi).abs() < 1e-4);
+
+        let inflections = spline.inflection_points();
+        assert_eq!(inflections.len(), 1);
+        assert!((inflections[0] - pi).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_extrapolation() {
+        let time: Vec<f64> = vec![0., 1., 3., 4., 5., 6.];
+        let values = vec![0., 1., 9., 16., 25., 36.];
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+        assert_eq!(spline.extrapolation(), Extrapolation::Polynomial);
+        assert!((spline.single_point(7.) - 49.).abs() < 1e-10);
+
+        let constant = spline.clone().with_extrapolation(Extrapolation::Constant);
+        assert!((constant.single_point(8.) - 36.).abs() < 1e-10);
+        assert_eq!(constant.point_derivative(-1., 1), 0.);
+
+        let linear = spline.clone().with_extrapolation(Extrapolation::Linear);
+        assert!((linear.single_point(8.) - 60.).abs() < 1e-9);
+        assert!((linear.point_derivative(-1., 1)).abs() < 1e-9);
+        assert!((linear.single_point(2.) - 4.).abs() < 1e-12);
+
+        let periodic = spline.clone().with_extrapolation(Extrapolation::Periodic);
+        assert!((periodic.single_point(8.) - 4.).abs() < 1e-12);
+        assert!((periodic.single_point(-4.) - 4.).abs() < 1e-12);
+
+        assert!(spline.clone().with_extrapolation(Extrapolation::Nan).single_point(6.5).is_nan());
+
+        let checked = spline.with_extrapolation(Extrapolation::Error);
+        assert!((checked.try_single_point(6.).unwrap() - 36.).abs() < 1e-10);
+        assert!(matches!(checked.try_points(&[1., 7.]), Err(FittingError::PointOutOfRange(_))));
+        assert!(matches!(checked.try_single_point(f64::NAN), Err(FittingError::PointOutOfRange(_))));
+        assert!(checked.point_derivative(-1., 1).is_nan());
+    }
+
+    #[test]
+    fn test_extrapolation_of_nan() {
+        let time: Vec<f64> = vec![0., 1., 3., 4., 5., 6.];
+        let values = vec![0., 1., 9., 16., 25., 36.];
+        for extrapolation in [Extrapolation::Polynomial, Extrapolation::Constant, Extrapolation::Linear,
+                              Extrapolation::Nan, Extrapolation::Periodic, Extrapolation::Error] {
+            let spline = GcvSpline::from_data(&time, &values).unwrap().with_extrapolation(extrapolation);
+            assert!(spline.single_point(f64::NAN).is_nan());
+            assert!(spline.point_derivative(f64::NAN, 1).is_nan());
+            assert!(spline.points(&[2., f64::NAN, 1.])[1].is_nan());
+            assert!(spline.points(&[f64::NAN])[0].is_nan());
+            assert!(spline.evaluate_all_derivatives(f64::NAN, 2).iter().all(|value| value.is_nan()));
+            assert!(spline.cursor().evaluate_all_derivatives(f64::NAN, 2).iter().all(|value| value.is_nan()));
+
+            let polynomial = spline.to_piecewise_polynomial();
+            assert!(polynomial.single_point(f64::NAN).is_nan());
+            assert!(polynomial.evaluate_all_derivatives(f64::NAN, 2).iter().all(|value| value.is_nan()));
+        }
+    }
+
+    #[test]
+    fn test_piecewise_polynomial() {
+        let time: Vec<f64> = (0..30).map(|e| e as f64 * 0.2).collect();
+        let values: Vec<f64> = time.iter().map(|t| (t * 1.3).sin() + 0.1 * t).collect();
+        let spline = GcvSpline::from_data_and_mode(&time, &values, FitMode::SmoothingParameter(1E-4)).unwrap();
+        let polynomial = spline.to_piecewise_polynomial();
+
+        let points: Vec<f64> = (0..80).map(|e| e as f64 * 0.09 - 0.7).collect();
+        for order in 0..6 {
+            for (expected, actual) in spline.derivative(&points, order).iter()
+                .zip(polynomial.derivative(&points, order)) {
+                assert!((expected - actual).abs() < 1e-8 * (1. + expected.abs()));
+            }
+        }
+        let all = polynomial.evaluate_all_derivatives(2.55, 6);
+        for (order, value) in all.iter().enumerate() {
+            assert!((spline.point_derivative(2.55, order) - value).abs() < 1e-8 * (1. + value.abs()));
+        }
+
+        let (breaks, coefs) = polynomial.to_mkpp();
+        let (c, x) = polynomial.to_scipy_ppoly();
+        assert_eq!(breaks, x);
+        assert_eq!(coefs.len(), time.len() - 1);
+        assert_eq!(c.len(), 6);
+        assert_eq!(c[5][3], coefs[3][5]);
+        assert!((coefs[3][5] - spline.single_point(time[3])).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_all_derivatives() {
+        let time: Vec<f64> = vec![0., 0.4, 1., 1.7, 2.5, 3., 3.2, 4.];
+        let values: Vec<f64> = time.iter().map(|t| (t * 2.).cos()).collect();
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+
+        for point in [-0.5, 0., 0.7, 1.7, 3.1, 4., 4.6] {
+            let all = spline.evaluate_all_derivatives(point, 7);
+            assert_eq!(all.len(), 8);
+            for (order, value) in all.iter().enumerate() {
+                assert!((spline.point_derivative(point, order) - value).abs() < 1e-9);
+            }
+        }
+
+        let points = vec![0.2, 1.1, 2.9];
+        let matrix = spline.all_derivatives(&points, 2);
+        assert_eq!(matrix[0], spline.points(&points));
+        assert_eq!(matrix[2], spline.second_derivative(&points));
+    }
+
+    #[test]
+    fn test_sorted_evaluation() {
+        let time: Vec<f64> = (0..40).map(|e| (e as f64 * 0.1).exp()).collect();
+        let values: Vec<f64> = time.iter().map(|t| t.ln().sin()).collect();
+        let spline = GcvSpline::from_data_and_mode(&time, &values, FitMode::Gcv).unwrap();
+
+        let points: Vec<f64> = (0..500).map(|e| e as f64 * 0.11 - 2.).collect();
+        let expected: Vec<f64> = points.iter().map(|point| spline.point_derivative(*point, 1)).collect();
+        assert_eq!(spline.sorted_derivative(&points, 1), expected);
+        assert_eq!(spline.derivative(&points, 1), expected);
+
+        let mut cursor = spline.cursor();
+        for point in points.iter().rev() {
+            assert_eq!(cursor.single_point(*point), spline.single_point(*point));
+        }
+        let all = cursor.evaluate_all_derivatives(3.3, 2);
+        assert!((all[1] - spline.point_derivative(3.3, 1)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_resampling() {
+        let time: Vec<f64> = (0..25).map(|e| e as f64 * 0.04).collect();
+        let values: Vec<f64> = time.iter().map(|t| t * t).collect();
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+
+        let uniform = spline.resample_uniform(11, 1).unwrap();
+        assert_eq!(uniform.time.len(), 11);
+        assert_eq!(uniform.time[10], 0.96);
+        assert!((uniform.values[5] - 0.48 * 0.48).abs() < 1e-12);
+        assert!((uniform.derivatives[0][5] - 0.96).abs() < 1e-10);
+
+        let rate = spline.resample_rate(50., 0).unwrap();
+        assert_eq!(rate.time.len(), 49);
+        assert!(rate.derivatives.is_empty());
+        assert!(spline.resample_rate(0., 0).is_err());
+        assert!(matches!(spline.resample_rate(1e300, 0), Err(FittingError::InvalidResampling(_))));
+        assert!(matches!(spline.resample_uniform(1, 0), Err(FittingError::InvalidResampling(_))));
+        assert!(matches!(spline.resample_uniform(0, 0), Err(FittingError::InvalidResampling(_))));
+
+        // A duration of a whole number of sampling periods ends on the last knot, despite rounding
+        let tenths = vec![0.1, 0.4, 0.7, 1., 1.3, 1.7];
+        let tenths_rate = GcvSpline::from_data(&tenths, &tenths).unwrap().resample_rate(10., 0).unwrap();
+        assert_eq!(tenths_rate.time.len(), 17);
+        assert_eq!(tenths_rate.time[16], 1.7);
+
+        let cycle = spline.normalize_interval(0.2, 0.7, 101, 2).unwrap();
+        assert_eq!(cycle.time.len(), 101);
+        assert!((cycle.time[100] - 100.).abs() < 1e-12);
+        assert!((cycle.values[100] - 0.49).abs() < 1e-12);
+        // Derivatives per percent of the interval
+        assert!((cycle.derivatives[0][50] - 0.9 * 0.005).abs() < 1e-12);
+        assert!((cycle.derivatives[1][50] - 2. * 0.005 * 0.005).abs() < 1e-12);
+        assert!(spline.normalize_interval(0.7, 0.2, 101, 0).is_err());
+    }
+
+    #[test]
+    fn test_periodic_spline() {
+        let tau = 2. * std::f64::consts::PI;
+        let time: Vec<f64> = (0..40).map(|e| e as f64 / 40.).collect();
+        let values: Vec<f64> = time.iter().map(|t| (tau * t).sin()).collect();
+        let spline = PeriodicGcvSpline::from_data(&time, &values, 1., FitMode::Interpolate).unwrap();
+        assert!((spline.single_point(0.9876) - (tau * 0.9876).sin()).abs() < 1e-8);
+        assert!((spline.single_point(1.3) - spline.single_point(0.3)).abs() < 1e-12);
+        // No end effects in the derivatives
+        assert!((spline.point_derivative(0., 1) - tau).abs() < 1e-6);
+        assert!((spline.point_derivative(0., 3) + tau.powi(3)).abs() < 1e-3 * tau.powi(3));
+        for order in 0..5 {
+            assert!((spline.point_derivative(1. - 1e-9, order) - spline.point_derivative(1e-9, order)).abs()
+                < 1e-5 * tau.powi(order as i32));
+        }
+
+        let (noisy_time, noisy) = noisy_sine(tau);
+        let smoothed = PeriodicGcvSpline::from_data(&noisy_time, &noisy, 1., FitMode::Gcv).unwrap();
+        let diagnostics = smoothed.diagnostics();
+        assert!(diagnostics.degrees_of_freedom > 1. && diagnostics.degrees_of_freedom < 49.);
+        assert!((smoothed.single_point(0.25) - 1.).abs() < 0.05);
+        assert!(PeriodicGcvSpline::from_data(&time, &values, 0.5, FitMode::Gcv).is_err());
+        assert!(spline.single_point(f64::NAN).is_nan());
+
+        // Away from the ends of the period, the periodic spline matches a natural spline with the same smoothing
+        let long_time: Vec<f64> = (0..2000).map(|e| e as f64 / 2000.).collect();
+        let long_values: Vec<f64> = long_time.iter().enumerate()
+            .map(|(i, t)| (tau * t).sin() + if i % 2 == 0 { 0.05 } else { -0.05 }).collect();
+        let mode = FitMode::SmoothingParameter(1e-12);
+        let periodic = PeriodicGcvSpline::from_data(&long_time, &long_values, 1., mode).unwrap();
+        let natural = GcvSpline::from_data_and_mode(&long_time, &long_values, mode).unwrap();
+        for point in [0.3, 0.5, 0.7] {
+            assert!((periodic.single_point(point) - natural.single_point(point)).abs() < 1e-9);
+            assert!((periodic.point_derivative(point, 2) - natural.point_derivative(point, 2)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_rotation_smoothing() {
+        // Rotation about the z axis at 2 rad/s, with the sign of every other quaternion flipped
+        let time: Vec<f64> = (0..50).map(|e| e as f64 * 0.04).collect();
+        let quaternions: Vec<[f64; 4]> = time.iter().enumerate().map(|(index, t)| {
+            let sign = if index % 2 == 0 { 1. } else { -1. };
+            [sign * t.cos(), 0., 0., sign * t.sin()]
+        }).collect();
+        let rotation = RotationGcvSpline::from_quaternions(&time, &quaternions, FitMode::Interpolate).unwrap();
+
+        let orientation = rotation.orientation(1.03);
+        assert!((orientation[0].abs() - 1.03_f64.cos().abs()).abs() < 1e-8);
+        assert!((orientation.iter().map(|c| c * c).sum::<f64>() - 1.).abs() < 1e-12);
+        let velocity = rotation.angular_velocity(1.03);
+        assert!(velocity[0].abs() < 1e-6 && velocity[1].abs() < 1e-6);
+        assert!((velocity[2] - 2.).abs() < 1e-6);
+        let acceleration = rotation.angular_acceleration(1.03);
+        assert!(acceleration.iter().all(|component| component.abs() < 1e-4));
+
+        assert!(RotationGcvSpline::from_quaternions(&time, &vec![[0.; 4]; 50], FitMode::Gcv).is_err());
+    }
+
+    #[test]
+    fn test_circular_spline() {
+        // Heading turning at 100 degrees per second, wrapping at 180 degrees
+        let time: Vec<f64> = (0..50).map(|e| e as f64 * 0.02).collect();
+        let headings: Vec<f64> = time.iter().map(|t| wrap_angle(170. + 100. * t, 360.)).collect();
+        assert!(headings[49] < 0.);
+        let spline = CircularGcvSpline::from_data(&time, &headings, 360., FitMode::Interpolate).unwrap();
+
+        assert!((spline.single_point(0.5) - 220.).abs() < 1e-9);
+        assert!((spline.wrapped_point(0.5) + 140.).abs() < 1e-9);
+        assert!(spline.derivative(&time, 1).iter().all(|velocity| (velocity - 100.).abs() < 1e-6));
+        assert_eq!(unwrap_angles(&[170., -170., f64::NAN, -150.], 360.)[3], 210.);
+        assert!(matches!(CircularGcvSpline::from_data(&time, &headings, 0., FitMode::Interpolate),
+                         Err(FittingError::InvalidFullTurn(_))));
+    }
+
+    #[test]
+    fn test_parametric_curve() {
+        // Half circle of radius 2, and a helix with curvature and torsion 1/2
+        let time: Vec<f64> = (0..60).map(|e| e as f64 * std::f64::consts::PI / 59.).collect();
+        let circle: Vec<Vec<f64>> = vec![time.iter().map(|t| 2. * t.cos()).collect(),
+                                        time.iter().map(|t| 2. * t.sin()).collect()];
+        let curve = ParametricGcvSpline::from_coordinates(&time, &circle, FitMode::Interpolate,
+                                                          ChannelSmoothing::Shared).unwrap();
+        let quarter = std::f64::consts::FRAC_PI_2;
+        let tangent = curve.tangent(quarter);
+        assert!((tangent[0] + 1.).abs() < 1e-6 && tangent[1].abs() < 1e-6);
+        let normal = curve.normal(quarter);
+        assert!(normal[0].abs() < 1e-6 && (normal[1] + 1.).abs() < 1e-6);
+        assert!((curve.curvature(1.3) - 0.5).abs() < 1e-5);
+        assert_eq!(curve.torsion(1.3), Some(0.));
+        assert!((curve.total_length() - 2. * std::f64::consts::PI).abs() < 1e-6);
+        assert!((curve.arc_length(1.0, 0.5) + 1.).abs() < 1e-6);
+        let times = curve.arc_length_parameterization(5);
+        assert!(times.iter().enumerate().all(|(index, t)| (t - index as f64 * quarter / 2.).abs() < 1e-6));
+        let (closest, position) = curve.closest_point(&[3., 3.]).unwrap();
+        assert!((closest - quarter / 2.).abs() < 1e-6);
+        assert!((position[0] - 2_f64.sqrt()).abs() < 1e-6);
+        assert!(matches!(curve.closest_point(&[3.]), Err(FittingError::VectorLengthMismatch(_))));
+        assert!(matches!(curve.closest_point(&[3., 3., 3.]), Err(FittingError::VectorLengthMismatch(_))));
+
+        let helix = vec![time.iter().map(|t| t.cos()).collect(), time.iter().map(|t| t.sin()).collect(),
+                         time.clone()];
+        let curve = ParametricGcvSpline::from_coordinates(&time, &helix, FitMode::Interpolate,
+                                                          ChannelSmoothing::Independent).unwrap();
+        assert!((curve.curvature(1.3) - 0.5).abs() < 1e-5);
+        assert!((curve.torsion(1.3).unwrap() - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_arc_length_inverse() {
+        // Parabola, and a path that stops and turns back at t = 0 with speed 2 * sqrt(5) * |t|
+        let time: Vec<f64> = (0..20).map(|e| -1. + e as f64 * 2. / 19.).collect();
+        let parabola = vec![time.clone(), time.iter().map(|t| t * t).collect()];
+        let curve = ParametricGcvSpline::from_coordinates(&time, &parabola, FitMode::Interpolate,
+                                                          ChannelSmoothing::Shared).unwrap();
+        let exact = 5_f64.sqrt() / 2. + 2_f64.asinh() / 4.;
+        assert!((curve.arc_length(0., 1.) - exact).abs() < 1e-12);
+        assert!((curve.time_at_arc_length(curve.arc_length(-1., 0.3)) - 0.3).abs() < 1e-10);
+        let points = curve.equally_spaced_points(7);
+        let times = curve.arc_length_parameterization(7);
+        assert!(times.windows(2).all(|pair| (curve.arc_length(pair[0], pair[1]) - curve.total_length() / 6.).abs()
+            < 1e-10));
+        assert!((points[6][1] - 1.).abs() < 1e-12);
+
+        let turning: Vec<Vec<f64>> = vec![time.iter().map(|t| t * t).collect(),
+                                         time.iter().map(|t| 2. * t * t).collect()];
+        let curve = ParametricGcvSpline::from_coordinates(&time, &turning, FitMode::Interpolate,
+                                                          ChannelSmoothing::Shared).unwrap();
+        assert!((curve.total_length() - 2. * 5_f64.sqrt()).abs() < 1e-10);
+        assert!((curve.time_at_arc_length(0.75 * 5_f64.sqrt()) + 0.5).abs() < 1e-8);
+        assert!((curve.time_at_arc_length(1.25 * 5_f64.sqrt()) - 0.5).abs() < 1e-8);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serialization_round_trip() {
+        let time: Vec<f64> = (0..30).map(|e| e as f64 * 0.1).collect();
+        let data: Vec<f64> = time.iter().map(|t| t.sin() + 0.01 * (t * 37.).sin()).collect();
+        let spline = GcvSpline::from_data_and_mode(&time, &data, FitMode::Gcv).unwrap()
+            .with_extrapolation(Extrapolation::Linear);
+
+        let serialized = serde_json::to_string(&spline).unwrap();
+        assert!(serialized.starts_with("{\"version\":1,"));
+        let loaded: GcvSpline<f64> = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(loaded, spline);
+        assert!(loaded.residual_diagnostics(&data, &vec![1.; time.len()]).is_ok());
+
+        let mut record: serde_json::Value = serde_json::from_str(&serialized).unwrap();
+        record["coefficients"].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<GcvSpline<f64>>(record.clone()).is_err());
+        record["coefficients"] = serde_json::to_value(&data).unwrap();
+        record["knots"][3] = serde_json::Value::from(0.);
+        assert!(serde_json::from_value::<GcvSpline<f64>>(record.clone()).is_err());
+        record["knots"] = serde_json::to_value(&time).unwrap();
+        assert!(serde_json::from_value::<GcvSpline<f64>>(record.clone()).is_ok());
+        for (statistic, value) in [("estimated_variance", -1.), ("smoothing_ratio", 1.5), ("degrees_of_freedom", 31.)] {
+            let mut tampered = record.clone();
+            tampered["diagnostics"][statistic] = serde_json::Value::from(value);
+            assert!(serde_json::from_value::<GcvSpline<f64>>(tampered).is_err());
+        }
+        let diagnostics = serde_json::to_value(spline.diagnostics().unwrap()).unwrap();
+        assert_eq!(serde_json::from_value::<crate::FitDiagnostics<f64>>(diagnostics.clone()).unwrap(),
+                   spline.diagnostics().unwrap());
+        let mut tampered = diagnostics;
+        tampered["gcv"] = serde_json::Value::from(-1.);
+        assert!(serde_json::from_value::<crate::FitDiagnostics<f64>>(tampered).is_err());
+        record["version"] = serde_json::Value::from(2);
+        assert!(serde_json::from_value::<GcvSpline<f64>>(record).is_err());
+    }
+
+    #[test]
+    fn test_spline_from_parts() {
+        let time: Vec<f64> = (0..20).map(|e| e as f64 * 0.2).collect();
+        let data: Vec<f64> = time.iter().map(|t| t.exp().sin()).collect();
+        let fitted = GcvSpline::from_data_and_half_order(&time, &data, 2).unwrap();
+        let rebuilt = GcvSpline::from_parts(&fitted.knots(), &fitted.coefficients(), fitted.half_order()).unwrap();
+        assert!(rebuilt.diagnostics().is_none());
+        let points = vec![-0.3, 0.55, 1.7, 3.9];
+        assert_eq!(rebuilt.points(&points), fitted.points(&points));
+        assert_eq!(rebuilt.derivative(&points, 3), fitted.derivative(&points, 3));
+
+        assert!(matches!(GcvSpline::from_parts(&time, &data[1..], 2),
+                         Err(FittingError::VectorLengthMismatch(_))));
+        assert!(matches!(GcvSpline::from_parts(&time[..3], &data[..3], 2),
+                         Err(FittingError::NotEnoughKnotsForOrder(_))));
+        assert!(matches!(GcvSpline::from_parts(&data, &data, 2), Err(FittingError::KnotsNotStrictlyIncreasing(_))));
+        let mut unbounded = time.clone();
+        unbounded[19] = f64::INFINITY;
+        assert!(matches!(GcvSpline::from_parts(&unbounded, &data, 2), Err(FittingError::NonFiniteKnots(_))));
+        assert!(matches!(GcvSpline::from_parts(&time, &unbounded, 2), Err(FittingError::NonFiniteCoefficients(_))));
+        let mut undefined = time.clone();
+        undefined[7] = f64::NAN;
+        assert!(matches!(GcvSpline::from_parts(&undefined, &data, 2), Err(FittingError::NonFiniteKnots(_))));
+        assert!(matches!(GcvSpline::from_parts(&time, &data, 0), Err(FittingError::InvalidHalfOrder(_))));
+        assert!(matches!(GcvSpline::from_data_and_half_order(&time, &data, 0), Err(FittingError::InvalidHalfOrder(_))));
+    }
+
+    #[test]
+    fn test_fit_with_workspace() {
+        let time: Vec<f64> = (0..40).map(|e| e as f64 * 0.05).collect();
+        let data: Vec<f64> = time.iter().map(|t| (3. * t).sin() + 0.02 * (t * 41.).cos()).collect();
+        let weights: Vec<f64> = time.iter().map(|t| 1. + t).collect();
+        let spline = GcvSpline::from_full_parameters_and_mode(&time, &data, &weights, 2, FitMode::Gcv).unwrap();
+
+        let mut coefficients = [0.; 40];
+        let mut workspace = vec![0.; required_workspace(40, 2)];
+        let diagnostics = fit_with_workspace(&time, &data, &weights, 2, FitMode::Gcv, &mut coefficients,
+                                             &mut workspace).unwrap();
+        assert_eq!(coefficients.to_vec(), spline.coefficients());
+        assert_eq!(Some(diagnostics), spline.diagnostics());
+
+        // Reusing the workspace gives the same fit
+        fit_with_workspace(&time, &data, &weights, 2, FitMode::Gcv, &mut coefficients, &mut workspace).unwrap();
+        assert_eq!(coefficients.to_vec(), spline.coefficients());
+        assert!(matches!(fit_with_workspace(&time, &data, &weights, 2, FitMode::Gcv, &mut coefficients,
+                                            &mut workspace[1..]),
+                         Err(FittingError::InsufficientWorkspace(_))));
+        assert!(matches!(fit_with_workspace(&time, &data, &weights, 0, FitMode::Gcv, &mut coefficients,
+                                            &mut workspace),
+                         Err(FittingError::InvalidHalfOrder(_))));
+
+        // Evaluating the coefficients gives the same values as the fitted spline, also outside the knots
+        let points = [1.3, -0.2, 0.71, f64::NAN, 2.4, 0.05];
+        let mut output = [0.; 6];
+        let mut evaluation_workspace = [0.; 4];
+        assert_eq!(required_evaluation_workspace(2), evaluation_workspace.len());
+        for derivative_order in 0 .. 4 {
+            assert_eq!(evaluate_with_workspace(&time, &coefficients, 2, points, derivative_order, &mut output,
+                                               &mut evaluation_workspace).unwrap(), 6);
+            let expected = spline.derivative(&points, derivative_order);
+            assert!(output.iter().zip(&expected).all(|(value, expected)| value == expected
+                || (value.is_nan() && expected.is_nan())));
+        }
+        assert!(matches!(evaluate_with_workspace(&time, &coefficients, 2, points, 0, &mut output,
+                                                 &mut evaluation_workspace[1..]),
+                         Err(FittingError::InsufficientWorkspace(_))));
+    }
+
+    #[test]
+    fn test_evaluate_into_slices() {
+        let time = [0., 0.5, 1.1, 1.5, 2.2, 2.5, 3.1, 3.5];
+        let values = time.map(|t: f64| t.cos());
+        let spline = GcvSpline::from_data(&time, &values).unwrap();
+
+        let mut output = [0.; 5];
+        assert_eq!(spline.points_into((0..5).map(|index| index as f64 * 0.75), &mut output), 5);
+        assert_eq!(output.to_vec(), spline.points(&[0., 0.75, 1.5, 2.25, 3.]));
+        assert_eq!(spline.derivative_into([3., 1.], 1, &mut output[..1]), 1);
+        assert_eq!(output[0], spline.point_derivative(3., 1));
+        assert_eq!(spline.derivative_into(time.iter().copied(), 2, &mut output), 5);
+        assert_eq!(output.to_vec(), spline.second_derivative(&time[..5]));
+    }
 }
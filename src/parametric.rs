@@ -0,0 +1,302 @@
+use alloc::{format, vec, vec::Vec};
+use num_traits::Float;
+use crate::calculus::adaptive_gauss_kronrod;
+use crate::spline_set::{ChannelSmoothing, GcvSplineSet};
+use crate::woltring::search::{find_knot_interval, guess_knot_interval};
+use crate::woltring::support::{FitDiagnostics, FitMode, FittingError};
+
+/// Represents a curve, such as a 2D or 3D marker path, fitted by one GCV spline per coordinate over shared time
+/// points (knots). Besides positions and their derivatives, the curve provides differential-geometry quantities
+/// (tangent, normal, curvature and torsion), arc length, reparameterization by arc length, and closest points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParametricGcvSpline<T: Float> {
+    splines: GcvSplineSet<T>,
+    // Arc length from the first knot to each knot
+    cumulative_length: Vec<T>,
+}
+
+impl<T: Float> ParametricGcvSpline<T> {
+
+    /// Fits a ParametricGcvSpline from a user-provided time (knots) vector and one data vector per coordinate. Default
+    /// values are used for half-order and weights.
+    pub fn from_coordinates<C: AsRef<[T]>>(time: &[T], coordinates: &[C], mode: FitMode<T>, smoothing: ChannelSmoothing)
+        -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, coordinates, &vec![T::from(1.)
+                                                                .expect("Cannot convert to type from f64");
+                                                            time.len()],
+                                   3, mode, smoothing)
+    }
+
+    /// Fits a ParametricGcvSpline from a user-provided time (knots) vector, one data vector per coordinate, a vector
+    /// of weights shared by all coordinates, and a half-order. `mode` selects how the smoothing parameter is chosen,
+    /// and `smoothing` whether it is shared between coordinates.
+    pub fn from_full_parameters<C: AsRef<[T]>>(time: &[T], coordinates: &[C], weights: &[T], half_order: usize,
+                                mode: FitMode<T>, smoothing: ChannelSmoothing) -> Result<Self, FittingError> {
+        let splines = GcvSplineSet::from_full_parameters(time, coordinates, weights, half_order, mode, smoothing)?;
+        let mut curve = ParametricGcvSpline {
+            splines,
+            cumulative_length: Vec::with_capacity(time.len()),
+        };
+        curve.cumulative_length.push(T::from(0.).expect("Cannot convert to type from f64"));
+        for knot_index in 1 .. time.len() {
+            let length = curve.cumulative_length[knot_index - 1]
+                + curve.piece_length(time[knot_index - 1], time[knot_index]);
+            curve.cumulative_length.push(length);
+        }
+        Ok(curve)
+    }
+
+    /// Returns the number of coordinates of the curve.
+    pub fn dimension(&self) -> usize {
+        self.splines.num_channels()
+    }
+
+    /// Evaluates the position on the curve at a single point.
+    pub fn position(&self, point: T) -> Vec<T> {
+        self.splines.single_point(point)
+    }
+
+    /// Evaluates the positions on the curve at a set of points. The result holds one position per point.
+    pub fn positions(&self, points: &[T]) -> Vec<Vec<T>> {
+        points.iter().map(|point| self.position(*point)).collect()
+    }
+
+    /// Evaluates a derivative of a given order of the position at a single point.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> Vec<T> {
+        self.splines.point_derivative(point, derivative_order)
+    }
+
+    /// Evaluates the unit tangent vector at a single point.
+    pub fn tangent(&self, point: T) -> Vec<T> {
+        let velocity = self.point_derivative(point, 1);
+        let speed = norm(&velocity);
+        velocity.iter().map(|component| *component / speed).collect()
+    }
+
+    /// Evaluates the unit principal normal vector at a single point, which points towards the center of curvature.
+    /// This is the part of the acceleration perpendicular to the tangent, normalized, so it is undefined (NaN) where
+    /// the curve is straight.
+    pub fn normal(&self, point: T) -> Vec<T> {
+        let tangent = self.tangent(point);
+        let acceleration = self.point_derivative(point, 2);
+        let along = dot(&acceleration, &tangent);
+        let perpendicular: Vec<T> = acceleration.iter().zip(&tangent)
+            .map(|(acceleration, tangent)| *acceleration - along * *tangent)
+            .collect();
+        let length = norm(&perpendicular);
+        perpendicular.iter().map(|component| *component / length).collect()
+    }
+
+    /// Evaluates the curvature at a single point, which is the inverse of the radius of the osculating circle.
+    pub fn curvature(&self, point: T) -> T {
+        let velocity = self.point_derivative(point, 1);
+        let acceleration = self.point_derivative(point, 2);
+        let speed_squared = dot(&velocity, &velocity);
+        let along = dot(&velocity, &acceleration);
+        // |r' x r''| generalized to any dimension through the Gram determinant
+        let area = (speed_squared * dot(&acceleration, &acceleration) - along * along)
+            .max(T::from(0.).expect("Cannot convert to type from f64")).sqrt();
+        area / (speed_squared * speed_squared.sqrt())
+    }
+
+    /// Evaluates the torsion at a single point, which measures how fast the curve leaves its osculating plane. Returns
+    /// 0 for planar (2D) curves and `None` for curves with other than 2 or 3 coordinates.
+    pub fn torsion(&self, point: T) -> Option<T> {
+        match self.dimension() {
+            2 => Some(T::from(0.).expect("Cannot convert to type from f64")),
+            3 => {
+                let velocity = self.point_derivative(point, 1);
+                let acceleration = self.point_derivative(point, 2);
+                let jerk = self.point_derivative(point, 3);
+                let binormal = [velocity[1] * acceleration[2] - velocity[2] * acceleration[1],
+                                velocity[2] * acceleration[0] - velocity[0] * acceleration[2],
+                                velocity[0] * acceleration[1] - velocity[1] * acceleration[0]];
+                Some(dot(&binormal, &jerk) / dot(&binormal, &binormal))
+            }
+            _ => None,
+        }
+    }
+
+    /// Computes the arc length of the curve from `start` to `end`, which is negative if `end` is smaller than `start`.
+    pub fn arc_length(&self, start: T, end: T) -> T {
+        if end < start {
+            return -self.arc_length(end, start);
+        }
+        let origin = self.arc_length_from_first_knot(start);
+        self.arc_length_from_first_knot(end) - origin
+    }
+
+    /// Returns the total arc length of the curve between the first and last knot.
+    pub fn total_length(&self) -> T {
+        self.cumulative_length[self.cumulative_length.len() - 1]
+    }
+
+    /// Returns `num_points` time points from the first to the last knot that divide the curve into pieces of equal
+    /// arc length. Evaluating the curve at these points resamples it evenly along its path.
+    pub fn arc_length_parameterization(&self, num_points: usize) -> Vec<T> {
+        let total = self.total_length();
+        let last_index = T::from(num_points.max(2) - 1).expect("Cannot convert to type from usize");
+        (0 .. num_points)
+            .map(|index| self.time_at_arc_length(total * T::from(index)
+                .expect("Cannot convert to type from usize") / last_index))
+            .collect()
+    }
+
+    /// Evaluates the curve at `num_points` points from the first to the last knot that are equally spaced along its
+    /// path. The result holds one position per point.
+    pub fn equally_spaced_points(&self, num_points: usize) -> Vec<Vec<T>> {
+        self.positions(&self.arc_length_parameterization(num_points))
+    }
+
+    /// Finds the time at which the arc length from the first knot reaches `length`, clamped to the knots. This
+    /// inverts the arc length by Newton iteration on the speed, safeguarded by bisection within the knot interval
+    /// that contains the time.
+    pub fn time_at_arc_length(&self, length: T) -> T {
+        let knots = &self.splines.knots;
+        let num_knots = knots.len();
+        if length.is_nan() || length <= T::from(0.).expect("Cannot convert to type from f64") {
+            return knots[0];
+        }
+        if length >= self.total_length() {
+            return knots[num_knots - 1];
+        }
+
+        let knot_index = self.cumulative_length.partition_point(|cumulative| *cumulative <= length);
+        let (mut lower, mut upper) = (knots[knot_index - 1], knots[knot_index]);
+        let remaining = length - self.cumulative_length[knot_index - 1];
+        let tolerance = arc_length_tolerance::<T>() * self.total_length();
+        let start = lower;
+        // Start from linear interpolation of the arc length within the knot interval
+        let mut time = lower + (upper - lower) * remaining
+            / (self.cumulative_length[knot_index] - self.cumulative_length[knot_index - 1]);
+        for _ in 0 .. 100 {
+            let excess = self.piece_length(start, time) - remaining;
+            if excess.abs() <= tolerance {
+                break;
+            }
+            if excess < T::from(0.).expect("Cannot convert to type from f64") {
+                lower = time;
+            } else {
+                upper = time;
+            }
+            let mut next = time - excess / norm(&self.point_derivative(time, 1));
+            if !(next > lower && next < upper) {
+                next = lower + (upper - lower) / T::from(2.).expect("Cannot convert to type from f64");
+            }
+            if next == time {
+                break;
+            }
+            time = next;
+        }
+        time
+    }
+
+    /// Finds the point of the curve between the first and last knot closest to `target`, returning its time and
+    /// position. The squared distance is a polynomial between knots; its stationary points are bracketed by sampling
+    /// each knot interval at 4 * *m* points and refined by bisection, so stationary points closer together than the
+    /// sampling can be missed. `target` must have one coordinate per coordinate of the curve.
+    pub fn closest_point(&self, target: &[T]) -> Result<(T, Vec<T>), FittingError> {
+        if target.len() != self.dimension() {
+            return Err(FittingError::VectorLengthMismatch(format!("Target has {} coordinates, expected {}",
+                                                                  target.len(), self.dimension())));
+        }
+        let knots = &self.splines.knots;
+        let zero = T::from(0.).expect("Cannot convert to type from f64");
+        let distance = |point: T| -> T {
+            self.position(point).iter().zip(target)
+                .fold(zero, |sum, (position, target)| sum + (*position - *target) * (*position - *target))
+        };
+        // Half the derivative of the squared distance
+        let slope = |point: T| -> T {
+            let offset: Vec<T> = self.position(point).iter().zip(target)
+                .map(|(position, target)| *position - *target)
+                .collect();
+            dot(&offset, &self.point_derivative(point, 1))
+        };
+
+        let mut best = (knots[0], distance(knots[0]));
+        let mut consider = |point: T| {
+            let candidate = distance(point);
+            if candidate < best.1 {
+                best = (point, candidate);
+            }
+        };
+        let num_samples = 4 * self.splines.half_order();
+        for pair in knots.windows(2) {
+            let step = (pair[1] - pair[0]) / T::from(num_samples).expect("Cannot convert to type from usize");
+            let mut lower = pair[0];
+            let mut lower_slope = slope(lower);
+            for sample in 1 ..= num_samples {
+                let upper = if sample == num_samples {
+                    pair[1]
+                } else {
+                    pair[0] + step * T::from(sample).expect("Cannot convert to type from usize")
+                };
+                let upper_slope = slope(upper);
+                // The distance has a minimum where its slope changes from negative to positive
+                if lower_slope < zero && upper_slope >= zero {
+                    let (mut left, mut right) = (lower, upper);
+                    loop {
+                        let middle = left + (right - left) / T::from(2.).expect("Cannot convert to type from f64");
+                        if middle <= left || middle >= right {
+                            break;
+                        }
+                        if slope(middle) < zero {
+                            left = middle;
+                        } else {
+                            right = middle;
+                        }
+                    }
+                    consider(left);
+                }
+                consider(upper);
+                lower = upper;
+                lower_slope = upper_slope;
+            }
+        }
+        Ok((best.0, self.position(best.0)))
+    }
+
+    /// Returns the statistics of the fit of each coordinate.
+    pub fn diagnostics(&self) -> Vec<FitDiagnostics<T>> {
+        self.splines.diagnostics()
+    }
+
+    /// Returns the GCV splines fitted to the coordinates.
+    pub fn splines(&self) -> &GcvSplineSet<T> {
+        &self.splines
+    }
+
+    /// Returns a copy of the time vector used to fit the curve.
+    pub fn time(&self) -> Vec<T> {
+        self.splines.time()
+    }
+
+    /// Computes the arc length from the first knot to a point.
+    fn arc_length_from_first_knot(&self, point: T) -> T {
+        let knots = &self.splines.knots;
+        let knot_interval = find_knot_interval(knots, point, guess_knot_interval(knots, point));
+        let start = knot_interval.max(1) - 1;
+        self.cumulative_length[start] + self.piece_length(knots[start], point)
+    }
+
+    /// Computes the arc length between two points within the same polynomial piece of the curve by adaptive
+    /// quadrature of the speed, which is not a polynomial and has a kink wherever the curve stops.
+    fn piece_length(&self, start: T, end: T) -> T {
+        adaptive_gauss_kronrod(&|point: T| norm(&self.point_derivative(point, 1)), start, end,
+                               arc_length_tolerance(), 30)
+    }
+}
+
+/// Returns the relative tolerance to which arc lengths are computed.
+fn arc_length_tolerance<T: Float>() -> T {
+    T::from(1000.).expect("Cannot convert to type from f64") * T::epsilon()
+}
+
+fn dot<T: Float>(a: &[T], b: &[T]) -> T {
+    a.iter().zip(b).fold(T::from(0.).expect("Cannot convert to type from f64"), |sum, (a, b)| sum + *a * *b)
+}
+
+fn norm<T: Float>(vector: &[T]) -> T {
+    dot(vector, vector).sqrt()
+}
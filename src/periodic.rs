@@ -0,0 +1,375 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use num_traits::Float;
+use crate::woltring::bandet::decompose_in_place;
+use crate::woltring::bansol::{solve_decomposed_system, solve_transposed_system};
+use crate::woltring::basis::create_basis;
+use crate::woltring::gcvspl::{select_smoothing, smoothing_tolerance};
+use crate::woltring::prep::create_weighted_matrix;
+use crate::woltring::search::guess_knot_interval;
+use crate::woltring::splc::{assemble_system, criterion_from_residual, effective_smoothing};
+use crate::woltring::splder::evaluate_spline;
+use crate::woltring::support::{check_fit_mode, check_increasing, check_order, check_vector_length, FitDiagnostics,
+                               FitMode, FittingError};
+use crate::woltring::trinv::trace_inverse;
+
+/// Represents a periodic GCV spline, such as a joint angle over a gait or pedalling cycle. The spline and its first
+/// 2 * *m* - 2 derivatives are continuous across the ends of the period, so there are no end effects, and evaluation
+/// wraps around the period.
+///
+/// The spline is fitted with the system of Woltring's GCVSPL on the knots continued periodically past either end,
+/// with the B-splines that overlap an end of the period wrapped around to the other end. The fitting system is then
+/// banded apart from two corners, which are handled with the Sherman-Morrison-Woodbury formula, so fitting takes time
+/// proportional to the number of time points, as for [`GcvSpline`](crate::GcvSpline).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PeriodicGcvSpline<T: Float> {
+    knots: Vec<T>,
+    // Knots continued periodically by 2 * m knots on either side, and the coefficients of the B-splines at them
+    extended_knots: Vec<T>,
+    extended_coefficients: Vec<T>,
+    period: T,
+    half_order: usize,
+    diagnostics: FitDiagnostics<T>,
+}
+
+impl<T: Float> PeriodicGcvSpline<T> {
+
+    /// Fits a PeriodicGcvSpline from user-provided time (knots) and data vectors, the period of the data, and a
+    /// [`FitMode`] selecting how the smoothing parameter is chosen. Default values are used for half-order and
+    /// weights.
+    pub fn from_data(time: &[T], data: &[T], period: T, mode: FitMode<T>) -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, data, &vec![T::from(1.)
+                                                         .expect("Cannot convert to type from f64"); time.len()],
+                                   3, period, mode)
+    }
+
+    /// Fits a PeriodicGcvSpline from user-provided time (knots) and data vectors, a vector of weights, a half-order,
+    /// the period of the data, and a [`FitMode`] selecting how the smoothing parameter is chosen. The time points must
+    /// lie within one period, so if the data include both ends of a cycle, the last sample should be left out.
+    pub fn from_full_parameters(time: &[T], data: &[T], weights: &[T], half_order: usize, period: T,
+                                mode: FitMode<T>) -> Result<Self, FittingError> {
+        let num_knots = time.len();
+        check_order(half_order, num_knots)?;
+        check_increasing(time)?;
+        check_vector_length(data, num_knots)?;
+        check_vector_length(weights, num_knots)?;
+        // Constants are the only periodic polynomials, so the degrees of freedom can reach the number of knots - 1
+        check_fit_mode(mode, 1, num_knots)?;
+        if !(period > time[num_knots - 1] - time[0] && period.is_finite()) {
+            return Err(FittingError::InvalidPeriod(
+                String::from("Period must be finite and longer than the duration of the time points")
+            ));
+        }
+
+        // The rows of the extended system at the time points only involve ordinary B-splines, whose columns are
+        // folded back onto the time points
+        let margin = 2 * half_order;
+        let zero = T::from(0.).expect("Cannot convert to type from f64");
+        let extended_knots = extend_periodically(time, margin, period);
+        let (extended_basis, _) = create_basis(half_order, &extended_knots)?;
+        let (extended_penalty, _) = create_weighted_matrix(half_order, &extended_knots,
+                                                           &extend_periodically(weights, margin, zero))?;
+        let basis = CyclicBand::fold(&extended_basis, 2 * half_order - 1, half_order, num_knots, margin);
+        let penalty = CyclicBand::fold(&extended_penalty, 2 * half_order + 1, half_order, num_knots, margin);
+        let penalty_norm = penalty.l1_norm() / basis.l1_norm();
+
+        let tolerance = smoothing_tolerance();
+        let num_corner_rows = 2 * half_order;
+        let mut coefficients = vec![zero; num_knots];
+        let mut system = vec![zero; penalty.band.len()];
+        let mut corners = vec![zero; penalty.corners.len()];
+        // Columns of the inverse of the banded part at the corner rows, and corner rows times that inverse
+        let mut inverse_columns = vec![zero; num_corner_rows * num_knots];
+        let mut corner_products = vec![zero; num_corner_rows * num_knots];
+        let mut capacitance = vec![zero; num_corner_rows * num_corner_rows];
+        let mut pivots = vec![0; num_corner_rows];
+        let mut working = vec![zero; num_knots];
+        let mut correction = vec![zero; num_corner_rows];
+        let mut stats = [zero; 6];
+        let mut evaluations = 0;
+        let mut evaluate = |smoothing: T| -> Result<(T, T), FittingError> {
+            evaluations += 1;
+            stats[3] = assemble_system(half_order, smoothing, tolerance, &basis.band, &penalty.band, penalty_norm,
+                                       &mut system);
+            let smoothing = effective_smoothing(smoothing, tolerance, penalty_norm);
+            for ((corner, basis), penalty) in corners.iter_mut().zip(&basis.corners).zip(&penalty.corners) {
+                *corner = *basis + smoothing * *penalty;
+            }
+            decompose_in_place(&mut system, half_order)?;
+
+            // The system is its banded part plus the corner rows, so its inverse follows from the inverse of the
+            // banded part and the capacitance matrix I + corners * inverse_columns
+            for (corner_row, column) in inverse_columns.chunks_mut(num_knots).enumerate() {
+                working.fill(zero);
+                working[corner_position(corner_row, half_order, num_knots).0] =
+                    T::from(1.).expect("Cannot convert to type from f64");
+                solve_decomposed_system(&system, &working, half_order, column)?;
+            }
+            for (corner_row, product) in corner_products.chunks_mut(num_knots).enumerate() {
+                let first_column = corner_position(corner_row, half_order, num_knots).1;
+                working.fill(zero);
+                working[first_column .. first_column + half_order]
+                    .copy_from_slice(&corners[corner_row * half_order .. (corner_row + 1) * half_order]);
+                solve_transposed_system(&system, &working, half_order, product)?;
+            }
+            for (index, value) in capacitance.iter_mut().enumerate() {
+                let (corner_row, corner_column) = (index / num_corner_rows, index % num_corner_rows);
+                *value = corner_products[corner_row * num_knots
+                    + corner_position(corner_column, half_order, num_knots).0];
+                if corner_row == corner_column {
+                    *value = *value + T::from(1.).expect("Cannot convert to type from f64");
+                }
+            }
+            decompose_dense(&mut capacitance, num_corner_rows, &mut pivots)?;
+
+            // Solve the system for the coefficients
+            solve_decomposed_system(&system, data, half_order, &mut coefficients)?;
+            for (corner_row, value) in correction.iter_mut().enumerate() {
+                let first_column = corner_position(corner_row, half_order, num_knots).1;
+                *value = corners[corner_row * half_order .. (corner_row + 1) * half_order].iter()
+                    .zip(&coefficients[first_column .. first_column + half_order])
+                    .fold(zero, |sum, (corner, coefficient)| sum + *corner * *coefficient);
+            }
+            solve_dense(&capacitance, &pivots, num_corner_rows, &mut correction);
+            for (column, factor) in inverse_columns.chunks(num_knots).zip(&correction) {
+                for (coefficient, value) in coefficients.iter_mut().zip(column) {
+                    *coefficient = *coefficient - *value * *factor;
+                }
+            }
+
+            // Mean squared weighted residual
+            working.fill(zero);
+            basis.for_each_entry(|row, column, value| working[row] = working[row] + value * coefficients[column]);
+            let residual = data.iter().zip(weights).zip(&working)
+                .fold(zero, |sum, ((value, weight), fitted)| {
+                    sum + *weight * (*value - *fitted) * (*value - *fitted)
+                }) / T::from(num_knots).expect("Cannot convert to type from usize");
+
+            // Degrees of freedom of the residual, p * TRACE[penalty * system**-1], where the inverse of the system
+            // is the inverse of the banded part minus inverse_columns * capacitance**-1 * corner_products
+            for column in 0 .. num_knots {
+                for (value, product) in correction.iter_mut().zip(corner_products.chunks(num_knots)) {
+                    *value = product[column];
+                }
+                solve_dense(&capacitance, &pivots, num_corner_rows, &mut correction);
+                for (value, product) in correction.iter().zip(corner_products.chunks_mut(num_knots)) {
+                    product[column] = *value;
+                }
+            }
+            let mut trace = trace_inverse(&penalty.band, &mut system, half_order)?;
+            for (corner_row, column) in inverse_columns.chunks(num_knots).enumerate() {
+                let first_column = corner_position(corner_row, half_order, num_knots).1;
+                for (penalty, inverse) in penalty.corners[corner_row * half_order .. (corner_row + 1) * half_order]
+                    .iter().zip(&column[first_column .. first_column + half_order]) {
+                    trace = trace + *penalty * *inverse;
+                }
+            }
+            penalty.for_each_entry(|row, column, value| {
+                let low_rank = inverse_columns.chunks(num_knots).zip(corner_products.chunks(num_knots))
+                    .fold(zero, |sum, (inverse, product)| sum + inverse[column] * product[row]);
+                trace = trace - value * low_rank;
+            });
+            let criterion = criterion_from_residual(mode, residual, smoothing * trace, num_knots, &mut stats);
+            Ok((criterion, stats[3]))
+        };
+        let smoothing = select_smoothing(mode, T::from(1.).expect("Cannot convert to type from f64") / penalty_norm,
+                                         &mut evaluate)?;
+
+        Ok(PeriodicGcvSpline {
+            knots: time.to_vec(),
+            extended_knots,
+            extended_coefficients: extend_periodically(&coefficients, margin, zero),
+            period,
+            half_order,
+            diagnostics: FitDiagnostics::from_stats(&stats, effective_smoothing(smoothing, tolerance, penalty_norm),
+                                                    evaluations),
+        })
+    }
+
+    /// Evaluates the spline at a single point.
+    pub fn single_point(&self, point: T) -> T {
+        self.point_derivative(point, 0)
+    }
+
+    /// Evaluates the spline at a set of points.
+    pub fn points(&self, points: &[T]) -> Vec<T> {
+        points.iter().map(|point| self.single_point(*point)).collect()
+    }
+
+    /// Evaluates a derivative of a given order at a single point. Points outside the period starting at the first
+    /// knot are wrapped into it, and points that are not finite evaluate to NaN.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> T {
+        if !point.is_finite() {
+            return T::nan();
+        }
+        let first = self.knots[0];
+        let mut wrapped = first + (point - first) % self.period;
+        if wrapped < first {
+            wrapped = wrapped + self.period;
+        }
+        evaluate_spline(derivative_order, self.half_order, wrapped, &self.extended_knots, &self.extended_coefficients,
+                        guess_knot_interval(&self.extended_knots, wrapped))
+    }
+
+    /// Evaluates a derivative of a given order at a set of points.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        points.iter().map(|point| self.point_derivative(*point, derivative_order)).collect()
+    }
+
+    /// Returns the period of the spline.
+    pub fn period(&self) -> T {
+        self.period
+    }
+
+    /// Returns a copy of the time vector used to fit the spline.
+    pub fn time(&self) -> Vec<T> {
+        self.knots.clone()
+    }
+
+    /// Returns a copy of the knots vector used to fit the spline.
+    pub fn knots(&self) -> Vec<T> {
+        self.time()
+    }
+
+    /// Returns the statistics of the fit that produced the spline.
+    pub fn diagnostics(&self) -> FitDiagnostics<T> {
+        self.diagnostics
+    }
+}
+
+/// Continues `values` periodically by `margin` values on either side, adding `period` for every wrap around.
+fn extend_periodically<T: Float>(values: &[T], margin: usize, period: T) -> Vec<T> {
+    let num_values = values.len() as isize;
+    (0 .. values.len() + 2 * margin).map(|index| {
+        let shifted = index as isize - margin as isize;
+        values[shifted.rem_euclid(num_values) as usize]
+            + period * T::from(shifted.div_euclid(num_values)).expect("Cannot convert to type from isize")
+    }).collect()
+}
+
+/// Cyclic band matrix, such as the B-spline basis or weighted penalty matrix of a periodic spline. The band is stored
+/// as in the banded systems of GCVSPL, with `width` entries centred on the diagonal of each row. Entries that wrap
+/// around to the far end of a row are stored in the corners, which hold 2 * *m* rows of *m* entries: the first *m*
+/// rows at the last *m* columns, then the last *m* rows at the first *m* columns.
+struct CyclicBand<T: Float> {
+    band: Vec<T>,
+    corners: Vec<T>,
+    width: usize,
+    half_order: usize,
+}
+
+impl<T: Float> CyclicBand<T> {
+
+    /// Folds the rows of a band matrix on the knots continued periodically by `margin` knots on either side back
+    /// onto the `num_knots` knots of one period.
+    fn fold(extended: &[T], width: usize, half_order: usize, num_knots: usize, margin: usize) -> Self {
+        let zero = T::from(0.).expect("Cannot convert to type from f64");
+        let mut band = vec![zero; num_knots * width];
+        let mut corners = vec![zero; 2 * half_order * half_order];
+        let reach = width / 2;
+        for row in 0 .. num_knots {
+            for (index, value) in extended[(row + margin) * width .. (row + margin + 1) * width].iter().enumerate() {
+                let column = (row + num_knots + index - reach) % num_knots;
+                let offset = column as isize - row as isize;
+                let entry = if offset.unsigned_abs() <= reach {
+                    &mut band[row * width + (offset + reach as isize) as usize]
+                } else if row < half_order {
+                    &mut corners[row * half_order + column + half_order - num_knots]
+                } else {
+                    &mut corners[(row + 2 * half_order - num_knots) * half_order + column]
+                };
+                *entry = *entry + *value;
+            }
+        }
+        CyclicBand {
+            band,
+            corners,
+            width,
+            half_order,
+        }
+    }
+
+    /// Calls `visit` with the row, column and value of every entry within the matrix.
+    fn for_each_entry<F: FnMut(usize, usize, T)>(&self, mut visit: F) {
+        let num_knots = self.band.len() / self.width;
+        let reach = self.width / 2;
+        for (row, entries) in self.band.chunks(self.width).enumerate() {
+            for (index, value) in entries.iter().enumerate() {
+                if row + index >= reach && row + index - reach < num_knots {
+                    visit(row, row + index - reach, *value);
+                }
+            }
+        }
+        for (corner_row, entries) in self.corners.chunks(self.half_order).enumerate() {
+            let (row, first_column) = corner_position(corner_row, self.half_order, num_knots);
+            for (index, value) in entries.iter().enumerate() {
+                visit(row, first_column + index, *value);
+            }
+        }
+    }
+
+    /// Returns the sum of absolute values of the entries.
+    fn l1_norm(&self) -> T {
+        self.band.iter().chain(&self.corners)
+            .fold(T::from(0.).expect("Cannot convert to type from f64"), |norm, value| norm + value.abs())
+    }
+}
+
+/// Returns the row of a cyclic band matrix held by row `corner_row` of its corners, and the column of its first entry.
+fn corner_position(corner_row: usize, half_order: usize, num_knots: usize) -> (usize, usize) {
+    if corner_row < half_order {
+        (corner_row, num_knots - half_order)
+    } else {
+        (corner_row + num_knots - 2 * half_order, 0)
+    }
+}
+
+/// Decomposes a square matrix stored by rows in place into LU factors with partial pivoting, storing the row swapped
+/// with each row in `pivots`. Returns [`FittingError::SingularSystem`] if a pivot is negligible relative to the
+/// largest entry of the matrix.
+fn decompose_dense<T: Float>(matrix: &mut [T], size: usize, pivots: &mut [usize]) -> Result<(), FittingError> {
+    let threshold = matrix.iter().fold(T::from(0.).expect("Cannot convert to type from f64"),
+                                       |largest, value| largest.max(value.abs())) * T::epsilon();
+    for column in 0 .. size {
+        let pivot = (column .. size).fold(column, |pivot, row| {
+            if matrix[row * size + column].abs() > matrix[pivot * size + column].abs() { row } else { pivot }
+        });
+        pivots[column] = pivot;
+        if pivot != column {
+            for index in 0 .. size {
+                matrix.swap(column * size + index, pivot * size + index);
+            }
+        }
+        let diagonal = matrix[column * size + column];
+        if !(diagonal.abs() > threshold && diagonal.is_finite()) {
+            return Err(FittingError::SingularSystem(
+                format!("Pivot {} of the periodic system is singular", column + 1)
+            ));
+        }
+        for row in column + 1 .. size {
+            let factor = matrix[row * size + column] / diagonal;
+            matrix[row * size + column] = factor;
+            for index in column + 1 .. size {
+                matrix[row * size + index] = matrix[row * size + index] - factor * matrix[column * size + index];
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Solves a linear system in place with the LU factors of `decompose_dense`.
+fn solve_dense<T: Float>(decomposed: &[T], pivots: &[usize], size: usize, rhs: &mut [T]) {
+    for (row, pivot) in pivots.iter().enumerate() {
+        rhs.swap(row, *pivot);
+    }
+    for row in 0 .. size {
+        for index in 0 .. row {
+            rhs[row] = rhs[row] - decomposed[row * size + index] * rhs[index];
+        }
+    }
+    for row in (0 .. size).rev() {
+        for index in row + 1 .. size {
+            rhs[row] = rhs[row] - decomposed[row * size + index] * rhs[index];
+        }
+        rhs[row] = rhs[row] / decomposed[row * size + row];
+    }
+}
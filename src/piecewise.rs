@@ -0,0 +1,159 @@
+use alloc::vec::Vec;
+use num_traits::Float;
+use crate::extrapolation::{extrapolate, Extrapolation};
+use crate::spline::GcvSpline;
+use crate::woltring::search::{find_knot_interval, guess_knot_interval};
+use crate::woltring::splder::evaluate_spline;
+
+/// Represents a GCV spline in piecewise polynomial form, holding the Taylor coefficients of the spline in each knot
+/// interval. Evaluating the polynomials with Horner's scheme is much cheaper than evaluating the B-spline form of a
+/// [`GcvSpline`], and gives the same values and derivatives up to rounding.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PiecewisePolynomial<T: Float> {
+    breakpoints: Vec<T>,
+    // Coefficients in increasing powers for each knot interval, including the polynomials continuing the spline
+    // before the first and after the last knot
+    pieces: Vec<Vec<T>>,
+    extrapolation: Extrapolation,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Converts the GCV spline to piecewise polynomial form, keeping its extrapolation.
+    pub fn to_piecewise_polynomial(&self) -> PiecewisePolynomial<T> {
+        let num_knots = self.knots.len();
+        let mut pieces = Vec::with_capacity(num_knots + 1);
+
+        // The polynomial before the first knot is expanded around a point within it, then shifted to the first knot
+        let outside = self.knots[0] - (self.knots[1] - self.knots[0]);
+        let mut factorial = T::from(1.).expect("Cannot convert to type from f64");
+        let mut tail: Vec<T> = (0 .. 2 * self.half_order).map(|power| {
+            if power > 0 {
+                factorial = factorial * T::from(power).expect("Cannot convert to type from usize");
+            }
+            evaluate_spline(power, self.half_order, outside, &self.knots, &self.coefficients, 0) / factorial
+        }).collect();
+        taylor_shift(&mut tail, self.knots[0] - outside);
+        pieces.push(tail);
+
+        for knot_interval in 1 ..= num_knots {
+            pieces.push(self.taylor_coefficients(knot_interval, 0));
+        }
+        PiecewisePolynomial {
+            breakpoints: self.knots.clone(),
+            pieces,
+            extrapolation: self.extrapolation,
+        }
+    }
+}
+
+impl<T: Float> PiecewisePolynomial<T> {
+
+    /// Evaluates the piecewise polynomial at a single point.
+    pub fn single_point(&self, point: T) -> T {
+        self.point_derivative(point, 0)
+    }
+
+    /// Evaluates the piecewise polynomial at a set of points.
+    pub fn points(&self, points: &[T]) -> Vec<T> {
+        points.iter().map(|point| self.single_point(*point)).collect()
+    }
+
+    /// Evaluates a derivative of a given order at a single point. Points outside the breakpoints are handled
+    /// according to the [`Extrapolation`] of the spline, with [`Extrapolation::Error`] giving NaN.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> T {
+        extrapolate(self.extrapolation, self.breakpoints[0], self.breakpoints[self.breakpoints.len() - 1], point,
+                    derivative_order, |point, derivative_order| self.evaluate_piece(point, derivative_order))
+            .unwrap_or_else(|| self.evaluate_piece(point, derivative_order))
+    }
+
+    /// Evaluates a derivative of a given order at a set of points.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        points.iter().map(|point| self.point_derivative(*point, derivative_order)).collect()
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` at a single point, in increasing order of
+    /// derivative.
+    pub fn evaluate_all_derivatives(&self, point: T, max_order: usize) -> Vec<T> {
+        if point.is_nan() || (self.extrapolation != Extrapolation::Polynomial && self.outside_breakpoints(point)) {
+            return (0 ..= max_order).map(|derivative_order| self.point_derivative(point, derivative_order)).collect();
+        }
+        let (piece, offset) = self.find_piece(point);
+        let mut shifted = self.pieces[piece].clone();
+        taylor_shift(&mut shifted, offset);
+
+        let mut factorial = T::from(1.).expect("Cannot convert to type from f64");
+        (0 ..= max_order).map(|derivative_order| {
+            if derivative_order > 0 {
+                factorial = factorial * T::from(derivative_order).expect("Cannot convert to type from usize");
+            }
+            shifted.get(derivative_order)
+                .map_or(T::from(0.).expect("Cannot convert to type from f64"), |coefficient| *coefficient * factorial)
+        }).collect()
+    }
+
+    /// Returns a copy of the breakpoints, which are the knots of the spline.
+    pub fn breakpoints(&self) -> Vec<T> {
+        self.breakpoints.clone()
+    }
+
+    /// Returns the number of coefficients of each polynomial piece, which is 2 * *m* for a spline of half-order *m*.
+    pub fn order(&self) -> usize {
+        self.pieces[0].len()
+    }
+
+    /// Exports the polynomial pieces between the first and last breakpoint in the layout of SciPy's
+    /// `PPoly(c, x)`, returning `(c, x)`. `c[i][j]` is the coefficient of power `order - 1 - i` of piece `j`, expanded
+    /// around `x[j]`. SciPy extrapolates with the end pieces rather than the polynomials continuing the spline, so
+    /// only values between the breakpoints match.
+    pub fn to_scipy_ppoly(&self) -> (Vec<Vec<T>>, Vec<T>) {
+        let interior = &self.pieces[1 .. self.breakpoints.len()];
+        let coefficients = (0 .. self.order()).rev()
+            .map(|power| interior.iter().map(|piece| piece[power]).collect())
+            .collect();
+        (coefficients, self.breakpoints.clone())
+    }
+
+    /// Exports the polynomial pieces between the first and last breakpoint in the layout of MATLAB's
+    /// `mkpp(breaks, coefs)`, returning `(breaks, coefs)`. Each row of `coefs` holds the coefficients of one piece in
+    /// decreasing powers, expanded around the start of the piece. MATLAB extrapolates with the end pieces rather than
+    /// the polynomials continuing the spline, so only values between the breakpoints match.
+    pub fn to_mkpp(&self) -> (Vec<T>, Vec<Vec<T>>) {
+        let coefficients = self.pieces[1 .. self.breakpoints.len()].iter()
+            .map(|piece| piece.iter().rev().copied().collect())
+            .collect();
+        (self.breakpoints.clone(), coefficients)
+    }
+
+    /// Finds the piece containing a point and the offset of the point from the origin of the piece.
+    fn find_piece(&self, point: T) -> (usize, T) {
+        let piece = find_knot_interval(&self.breakpoints, point, guess_knot_interval(&self.breakpoints, point));
+        (piece, point - self.breakpoints[piece.max(1) - 1])
+    }
+
+    fn evaluate_piece(&self, point: T, derivative_order: usize) -> T {
+        let (piece, offset) = self.find_piece(point);
+        self.pieces[piece].iter().enumerate().skip(derivative_order).rev()
+            .fold(T::from(0.).expect("Cannot convert to type from f64"), |value, (power, coefficient)| {
+                // Multiply by the falling factorial power * (power - 1) * ... * (power - derivative_order + 1)
+                let scale = (power + 1 - derivative_order ..= power)
+                    .fold(T::from(1.).expect("Cannot convert to type from f64"),
+                          |scale, factor| scale * T::from(factor).expect("Cannot convert to type from usize"));
+                value * offset + *coefficient * scale
+            })
+    }
+
+    fn outside_breakpoints(&self, point: T) -> bool {
+        point < self.breakpoints[0] || point > self.breakpoints[self.breakpoints.len() - 1]
+    }
+}
+
+/// Replaces the coefficients of a polynomial p(x), in increasing powers, by those of p(x + `shift`).
+fn taylor_shift<T: Float>(polynomial: &mut [T], shift: T) {
+    let len = polynomial.len();
+    for start in 0 .. len {
+        for index in (start .. len - 1).rev() {
+            polynomial[index] = polynomial[index] + shift * polynomial[index + 1];
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use alloc::{string::String, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::FittingError;
+
+/// GCV spline evaluated at a set of evenly spaced time points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resampled<T: Float> {
+    /// Time points at which the spline was evaluated.
+    pub time: Vec<T>,
+    /// Value of the spline at each time point.
+    pub values: Vec<T>,
+    /// Derivatives of the spline at each time point, with respect to `time`. `derivatives[k]` holds the derivative of
+    /// order `k + 1`.
+    pub derivatives: Vec<Vec<T>>,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Evaluates the spline and its derivatives up to `max_derivative_order` at `num_points` evenly spaced time points
+    /// from the first to the last knot. At least 2 points are needed.
+    pub fn resample_uniform(&self, num_points: usize, max_derivative_order: usize)
+        -> Result<Resampled<T>, FittingError> {
+        if num_points < 2 {
+            return Err(FittingError::InvalidResampling(String::from("At least 2 points are needed")));
+        }
+        let first = self.knots[0];
+        let last = self.knots[self.knots.len() - 1];
+        let last_index = T::from(num_points - 1).expect("Cannot convert to type from usize");
+        let time = (0 .. num_points)
+            .map(|index| {
+                first + (last - first) * T::from(index).expect("Cannot convert to type from usize") / last_index
+            })
+            .collect();
+        Ok(self.resample_at(time, max_derivative_order, T::from(1.).expect("Cannot convert to type from f64")))
+    }
+
+    /// Evaluates the spline and its derivatives up to `max_derivative_order` at a sampling rate of `rate` samples per
+    /// unit of time, starting at the first knot and ending at or before the last knot. Rates giving more samples than
+    /// fit in `usize` return [`FittingError::InvalidResampling`].
+    pub fn resample_rate(&self, rate: T, max_derivative_order: usize) -> Result<Resampled<T>, FittingError> {
+        if !(rate > T::from(0.).expect("Cannot convert to type from f64") && rate.is_finite()) {
+            return Err(FittingError::InvalidResampling(String::from("Sampling rate must be finite and positive")));
+        }
+        let first = self.knots[0];
+        let last = self.knots[self.knots.len() - 1];
+        let duration = last - first;
+        // Allow for rounding when the duration is a whole number of sampling periods
+        let num_points = (duration * rate * (T::from(1.).expect("Cannot convert to type from f64")
+            + T::from(8.).expect("Cannot convert to type from f64") * T::epsilon())).floor().to_usize()
+            .and_then(|num_periods| num_periods.checked_add(1))
+            .ok_or_else(|| FittingError::InvalidResampling(
+                String::from("Number of samples at the sampling rate does not fit in usize")
+            ))?;
+        // The allowance for rounding may place the last sample just past the last knot
+        let time = (0 .. num_points)
+            .map(|index| (first + T::from(index).expect("Cannot convert to type from usize") / rate).min(last))
+            .collect();
+        Ok(self.resample_at(time, max_derivative_order, T::from(1.).expect("Cannot convert to type from f64")))
+    }
+
+    /// Evaluates the spline and its derivatives up to `max_derivative_order` at `num_points` evenly spaced time points
+    /// from `start` to `end`, such as two gait events, and expresses them over a normalized time running from 0 to
+    /// 100 percent of the interval. Derivatives are taken with respect to the normalized time, so that a derivative
+    /// of order *k* is scaled by ((`end` - `start`) / 100)^*k*. At least 2 points are needed.
+    pub fn normalize_interval(&self, start: T, end: T, num_points: usize, max_derivative_order: usize)
+        -> Result<Resampled<T>, FittingError> {
+        if !(end > start && start.is_finite() && end.is_finite()) {
+            return Err(FittingError::InvalidResampling(
+                String::from("Interval end must be finite and after its start")
+            ));
+        }
+        if num_points < 2 {
+            return Err(FittingError::InvalidResampling(String::from("At least 2 points are needed")));
+        }
+        let hundred = T::from(100.).expect("Cannot convert to type from f64");
+        let last_index = T::from(num_points - 1).expect("Cannot convert to type from usize");
+        let time = (0 .. num_points)
+            .map(|index| {
+                start + (end - start) * T::from(index).expect("Cannot convert to type from usize") / last_index
+            })
+            .collect();
+
+        let mut resampled = self.resample_at(time, max_derivative_order, (end - start) / hundred);
+        resampled.time = (0 .. num_points)
+            .map(|index| hundred * T::from(index).expect("Cannot convert to type from usize") / last_index)
+            .collect();
+        Ok(resampled)
+    }
+
+    /// Evaluates the spline and its derivatives at sorted time points, multiplying each derivative of order *k* by
+    /// `time_scale`^*k*.
+    fn resample_at(&self, time: Vec<T>, max_derivative_order: usize, time_scale: T) -> Resampled<T> {
+        let mut evaluated = self.all_derivatives(&time, max_derivative_order);
+        let mut scale = T::from(1.).expect("Cannot convert to type from f64");
+        for derivative in evaluated.iter_mut().skip(1) {
+            scale = scale * time_scale;
+            for value in derivative.iter_mut() {
+                *value = *value * scale;
+            }
+        }
+        let values = evaluated.remove(0);
+        Resampled {
+            time,
+            values,
+            derivatives: evaluated,
+        }
+    }
+}
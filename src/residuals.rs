@@ -0,0 +1,69 @@
+use alloc::{string::String, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::{check_vector_length, FitMode, FittingError};
+
+/// Per-sample diagnostics of a fitted GCV spline, used to find data points that dominate the fit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidualDiagnostics<T: Float> {
+    /// Data value minus fitted value at each sample.
+    pub residuals: Vec<T>,
+    /// Diagonal of the influence matrix at each sample, between 0 and 1. Values close to 1 mean the fit follows the
+    /// sample regardless of its neighbours.
+    pub leverage: Vec<T>,
+    /// Weighted residuals divided by their estimated standard deviation, sqrt(variance * (1 - leverage)).
+    pub standardized_residuals: Vec<T>,
+    /// Cook's distance of each sample, measuring how much the fitted values change when the sample is left out.
+    pub cooks_distance: Vec<T>,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Computes residuals, leverage, standardized residuals and Cook's distances for the data and weights the spline
+    /// was fitted to. Standardized residuals and Cook's distances are not finite for interpolating splines, where
+    /// every sample has a leverage of 1. Data and weights that do not reproduce the fit return
+    /// [`FittingError::MissingFitData`].
+    pub fn residual_diagnostics(&self, data: &[T], weights: &[T]) -> Result<ResidualDiagnostics<T>, FittingError> {
+        let diagnostics = self.diagnostics.ok_or_else(|| FittingError::MissingFitData(
+            String::from("Residual diagnostics need a spline fitted to data")
+        ))?;
+        check_vector_length(data, self.knots.len())?;
+        let (design, leverage) = self.fitted_design(weights, &diagnostics)?;
+
+        // The spline is linear in the data, so refitting the data reproduces its coefficients
+        let (coefficients, _) = design.fit(data, FitMode::SmoothingParameter(diagnostics.smoothing_parameter))?;
+        let scale = data.iter().chain(&self.coefficients)
+            .fold(T::from(0.).expect("Cannot convert to type from f64"), |scale, value| scale.max(value.abs()));
+        let mismatch = coefficients.iter().zip(&self.coefficients)
+            .fold(T::from(0.).expect("Cannot convert to type from f64"),
+                  |mismatch, (refitted, fitted)| mismatch + (*refitted - *fitted).abs());
+        let num_knots = T::from(self.knots.len()).expect("Cannot convert to type from usize");
+        if !(mismatch <= T::epsilon().sqrt() * scale * num_knots && mismatch.is_finite()) {
+            return Err(FittingError::MissingFitData(String::from("Data do not reproduce the fit of the spline")));
+        }
+
+        let residuals: Vec<T> = data.iter().zip(self.points(&self.knots))
+            .map(|(value, fitted)| *value - fitted)
+            .collect();
+        let model_dof = T::from(self.knots.len()).expect("Cannot convert to type from usize")
+            - diagnostics.degrees_of_freedom;
+        let one = T::from(1.).expect("Cannot convert to type from f64");
+
+        let mut standardized_residuals = Vec::with_capacity(residuals.len());
+        let mut cooks_distance = Vec::with_capacity(residuals.len());
+        for ((residual, leverage), weight) in residuals.iter().zip(&leverage).zip(weights) {
+            let weighted_square = *residual * *residual * *weight;
+            standardized_residuals.push(*residual * weight.sqrt()
+                / (diagnostics.estimated_variance * (one - *leverage)).sqrt());
+            cooks_distance.push(weighted_square / (model_dof * diagnostics.estimated_variance)
+                * *leverage / ((one - *leverage) * (one - *leverage)));
+        }
+
+        Ok(ResidualDiagnostics {
+            residuals,
+            leverage,
+            standardized_residuals,
+            cooks_distance,
+        })
+    }
+}
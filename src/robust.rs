@@ -0,0 +1,194 @@
+use alloc::{format, string::String, vec, vec::Vec};
+use core::cmp::Ordering;
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::support::{check_vector_length, FitMode, FittingError};
+
+/// Loss function used to down-weight outlying samples in a robust fit. The tuning constants are expressed in robust
+/// standard deviations of the residuals and must be finite and positive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RobustLoss<T: Float> {
+    /// Huber loss, which down-weights residuals larger than the tuning constant (commonly 1.345) in proportion to
+    /// their size.
+    Huber(T),
+    /// Tukey's bisquare loss, which smoothly down-weights residuals up to the tuning constant (commonly 4.685) and
+    /// ignores larger ones.
+    Bisquare(T),
+}
+
+/// Settings of a robust fit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RobustOptions<T: Float> {
+    /// Loss function used to compute robustness weights from residuals.
+    pub loss: RobustLoss<T>,
+    /// Maximum number of weighted fits, at least 1.
+    pub max_iterations: usize,
+    /// The iterations stop once no robustness weight changes by more than this amount, which must not be negative.
+    pub tolerance: T,
+}
+
+impl<T: Float> Default for RobustOptions<T> {
+    fn default() -> Self {
+        RobustOptions {
+            loss: RobustLoss::Bisquare(T::from(4.685).expect("Cannot convert to type from f64")),
+            max_iterations: 20,
+            tolerance: T::from(1E-4).expect("Cannot convert to type from f64"),
+        }
+    }
+}
+
+/// Represents a GCV spline fitted by iteratively reweighted fits, which limits the influence of outlying samples
+/// such as marker swaps or reflections.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RobustFit<T: Float> {
+    spline: GcvSpline<T>,
+    robustness_weights: Vec<T>,
+    iterations: usize,
+    converged: bool,
+}
+
+impl<T: Float> RobustFit<T> {
+
+    /// Fits a robust GCV spline from user-provided time (knots) and data vectors. Default values are used for
+    /// half-order and weights.
+    pub fn from_data(time: &[T], data: &[T], mode: FitMode<T>, options: RobustOptions<T>)
+        -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, data, &vec![T::from(1.)
+                                                         .expect("Cannot convert to type from f64"); time.len()],
+                                   3, mode, options)
+    }
+
+    /// Fits a robust GCV spline from user-provided time (knots) and data vectors, a vector of weights, and a
+    /// half-order. Each iteration refits the spline with the provided weights multiplied by robustness weights
+    /// computed from the residuals of the previous fit, scaled by their median absolute deviation. Samples with a
+    /// robustness weight of 0 are left out of the fit, and fitting fails with
+    /// [`FittingError::InsufficientSamples`] once fewer than twice the half-order samples remain. `mode` selects how
+    /// the smoothing parameter of each fit is chosen and must allow smoothing, so modes that interpolate the data,
+    /// such as a known variance or smoothing parameter of 0, are rejected.
+    pub fn from_full_parameters(time: &[T], data: &[T], weights: &[T], half_order: usize,
+                                mode: FitMode<T>, options: RobustOptions<T>) -> Result<Self, FittingError> {
+        if interpolates(mode) {
+            return Err(FittingError::InvalidFitMode(String::from("Robust fitting needs a smoothing fit mode")));
+        }
+        if options.max_iterations == 0 {
+            return Err(FittingError::InvalidFitMode(String::from("Robust fitting needs at least 1 iteration")));
+        }
+        let (RobustLoss::Huber(constant) | RobustLoss::Bisquare(constant)) = options.loss;
+        if !(constant > T::from(0.).expect("Cannot convert to type from f64") && constant.is_finite()) {
+            return Err(FittingError::InvalidFitMode(String::from("Tuning constant must be finite and positive")));
+        }
+        if options.tolerance.is_nan() || options.tolerance < T::from(0.).expect("Cannot convert to type from f64") {
+            return Err(FittingError::InvalidFitMode(String::from("Tolerance must be non-negative")));
+        }
+        check_vector_length(data, time.len())?;
+        check_vector_length(weights, time.len())?;
+
+        let mut robustness_weights = vec![T::from(1.).expect("Cannot convert to type from f64"); time.len()];
+        let mut iterations = 0;
+        loop {
+            // Samples with a robustness weight of 0 are left out of the fit, since they would still count towards
+            // the degrees of freedom of the GCV criterion
+            let kept: Vec<usize> = (0..time.len())
+                .filter(|index| robustness_weights[*index] > T::from(0.).expect("Cannot convert to type from f64"))
+                .collect();
+            if kept.len() < 2 * half_order && kept.len() < time.len() {
+                return Err(FittingError::InsufficientSamples(format!(
+                    "Only {} samples kept a non-zero robustness weight, at least {} are needed",
+                    kept.len(), 2 * half_order
+                )));
+            }
+            let fit_weights: Vec<T> = kept.iter().map(|index| weights[*index] * robustness_weights[*index]).collect();
+            let spline = GcvSpline::from_full_parameters_and_mode(
+                &kept.iter().map(|index| time[*index]).collect::<Vec<T>>(),
+                &kept.iter().map(|index| data[*index]).collect::<Vec<T>>(),
+                &fit_weights, half_order, mode)?;
+            iterations += 1;
+
+            let residuals: Vec<T> = data.iter().zip(spline.points(time)).zip(weights)
+                .map(|((value, fitted), weight)| (*value - fitted) * weight.sqrt())
+                .collect();
+            let updated = robustness_from_residuals(&residuals, options.loss);
+            let change = updated.iter().zip(&robustness_weights)
+                .fold(T::from(0.).expect("Cannot convert to type from f64"),
+                      |change, (new, old)| change.max((*new - *old).abs()));
+            let converged = change <= options.tolerance;
+
+            if converged || iterations >= options.max_iterations {
+                return Ok(RobustFit {
+                    spline,
+                    robustness_weights,
+                    iterations,
+                    converged,
+                });
+            }
+            robustness_weights = updated;
+        }
+    }
+
+    /// Returns the robust GCV spline.
+    pub fn spline(&self) -> &GcvSpline<T> {
+        &self.spline
+    }
+
+    /// Returns the robustness weights used in the final fit, between 0 and 1.
+    pub fn robustness_weights(&self) -> Vec<T> {
+        self.robustness_weights.clone()
+    }
+
+    /// Returns the number of weighted fits performed.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// Returns whether the robustness weights converged within the maximum number of iterations.
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+}
+
+/// Returns whether a fit mode interpolates the data, leaving no residuals to compute robustness weights from.
+fn interpolates<T: Float>(mode: FitMode<T>) -> bool {
+    let zero = T::from(0.).expect("Cannot convert to type from f64");
+    match mode {
+        FitMode::Interpolate => true,
+        FitMode::KnownVariance(value) | FitMode::SmoothingParameter(value) | FitMode::DegreesOfFreedom(value) => {
+            value == zero
+        }
+        FitMode::Gcv => false,
+    }
+}
+
+/// Computes robustness weights from residuals, scaled by their median absolute deviation.
+fn robustness_from_residuals<T: Float>(residuals: &[T], loss: RobustLoss<T>) -> Vec<T> {
+    let mut absolute: Vec<T> = residuals.iter().map(|residual| residual.abs()).collect();
+    absolute.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let middle = absolute.len() / 2;
+    let median = if middle * 2 == absolute.len() {
+        (absolute[middle - 1] + absolute[middle]) / T::from(2.).expect("Cannot convert to type from f64")
+    } else {
+        absolute[middle]
+    };
+    // Consistent estimate of the standard deviation for normally distributed residuals
+    let scale = median / T::from(0.6745).expect("Cannot convert to type from f64");
+
+    residuals.iter().map(|residual| {
+        if scale <= T::from(0.).expect("Cannot convert to type from f64") {
+            return T::from(1.).expect("Cannot convert to type from f64");
+        }
+        let scaled = (*residual / scale).abs();
+        match loss {
+            RobustLoss::Huber(tuning) => {
+                if scaled <= tuning { T::from(1.).expect("Cannot convert to type from f64") } else { tuning / scaled }
+            }
+            RobustLoss::Bisquare(tuning) => {
+                if scaled < tuning {
+                    let ratio = scaled / tuning;
+                    let complement = T::from(1.).expect("Cannot convert to type from f64") - ratio * ratio;
+                    complement * complement
+                } else {
+                    T::from(0.).expect("Cannot convert to type from f64")
+                }
+            }
+        }
+    }).collect()
+}
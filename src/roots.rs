@@ -0,0 +1,195 @@
+use alloc::{vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::splder::evaluate_spline;
+
+/// Kind of a local extremum of a GCV spline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtremumKind {
+    /// The spline decreases before and increases after the extremum.
+    Minimum,
+    /// The spline increases before and decreases after the extremum.
+    Maximum,
+}
+
+/// Local extremum of a GCV spline.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Extremum<T: Float> {
+    /// Time of the extremum.
+    pub time: T,
+    /// Value of the spline at the extremum.
+    pub value: T,
+    /// Whether the extremum is a minimum or a maximum.
+    pub kind: ExtremumKind,
+}
+
+impl<T: Float> GcvSpline<T> {
+
+    /// Finds all times between the first and last knot at which a derivative of a given order of the spline equals
+    /// `level`, in increasing order. Between knots the spline is a polynomial, whose roots are bracketed by the roots
+    /// of its own derivative and then bisected to full floating-point precision. Values within 1000 machine epsilons
+    /// of `level`, relative to the magnitude of the polynomial on its knot interval, are treated as equal to it, so
+    /// that roots where the derivative only touches `level` are found too. If the derivative equals `level` over a
+    /// whole knot interval, only the start of that interval is reported. Derivatives of order 2 * *m* and higher are
+    /// identically zero and have no reported roots.
+    pub fn roots(&self, derivative_order: usize, level: T) -> Vec<T> {
+        let mut roots = Vec::new();
+        if derivative_order >= 2 * self.half_order {
+            return roots;
+        }
+        let num_knots = self.knots.len();
+        let mut previous_end: Option<(T, T)> = None;
+        for knot_interval in 1 .. num_knots {
+            let start = self.knots[knot_interval - 1];
+            let width = self.knots[knot_interval] - start;
+            let mut polynomial = self.taylor_coefficients(knot_interval, derivative_order);
+            polynomial[0] = polynomial[0] - level;
+            let tolerance = root_tolerance(&polynomial, width, level);
+
+            // The derivative of order 2 * m - 1 is discontinuous and can cross the level at a knot
+            if let Some((end_value, end_tolerance)) = previous_end {
+                if end_value.abs() > end_tolerance && polynomial[0].abs() > tolerance
+                    && (end_value < T::from(0.).expect("Cannot convert to type from f64"))
+                    != (polynomial[0] < T::from(0.).expect("Cannot convert to type from f64")) {
+                    roots.push(start);
+                }
+            }
+            roots.extend(polynomial_roots(&polynomial, width, tolerance).into_iter().map(|root| start + root));
+
+            let end_value = horner(&polynomial, width);
+            if knot_interval == num_knots - 1 && end_value.abs() <= tolerance {
+                roots.push(self.knots[knot_interval]);
+            }
+            previous_end = Some((end_value, tolerance));
+        }
+        roots.dedup();
+        roots
+    }
+
+    /// Finds the local minima and maxima of the spline between the first and last knot, in increasing order of time.
+    /// These are the roots of the first derivative at which it changes sign, found as described in
+    /// [`GcvSpline::roots`].
+    pub fn local_extrema(&self) -> Vec<Extremum<T>> {
+        self.sign_changes(1).into_iter()
+            .map(|(time, rising)| Extremum {
+                time,
+                value: self.single_point(time),
+                kind: if rising { ExtremumKind::Minimum } else { ExtremumKind::Maximum },
+            })
+            .collect()
+    }
+
+    /// Finds the inflection points of the spline between the first and last knot, in increasing order. These are the
+    /// roots of the second derivative at which it changes sign, found as described in [`GcvSpline::roots`].
+    pub fn inflection_points(&self) -> Vec<T> {
+        self.sign_changes(2).into_iter().map(|(time, _)| time).collect()
+    }
+
+    /// Computes the Taylor coefficients of a derivative of a given order of the spline within a knot interval,
+    /// expanded around the knot at the start of the interval.
+    pub(crate) fn taylor_coefficients(&self, knot_interval: usize, derivative_order: usize) -> Vec<T> {
+        let start = self.knots[knot_interval - 1];
+        let mut factorial = T::from(1.).expect("Cannot convert to type from f64");
+        (0 .. (2 * self.half_order).saturating_sub(derivative_order)).map(|power| {
+            if power > 0 {
+                factorial = factorial * T::from(power).expect("Cannot convert to type from usize");
+            }
+            evaluate_spline(derivative_order + power, self.half_order, start, &self.knots, &self.coefficients,
+                            knot_interval) / factorial
+        }).collect()
+    }
+
+    /// Finds the roots of a derivative at which it changes sign, along with whether it goes from negative to
+    /// positive.
+    fn sign_changes(&self, derivative_order: usize) -> Vec<(T, bool)> {
+        let roots = self.roots(derivative_order, T::from(0.).expect("Cannot convert to type from f64"));
+        let (first, last) = match (self.knots.first(), self.knots.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return Vec::new(),
+        };
+        let two = T::from(2.).expect("Cannot convert to type from f64");
+        let zero = T::from(0.).expect("Cannot convert to type from f64");
+
+        // The derivative keeps its sign between consecutive roots
+        roots.iter().enumerate().filter_map(|(index, root)| {
+            let before = if index == 0 { first } else { roots[index - 1] };
+            let after = roots.get(index + 1).copied().unwrap_or(last);
+            let left = self.point_derivative((before + *root) / two, derivative_order);
+            let right = self.point_derivative((*root + after) / two, derivative_order);
+            if left < zero && right > zero {
+                Some((*root, true))
+            } else if left > zero && right < zero {
+                Some((*root, false))
+            } else {
+                None
+            }
+        }).collect()
+    }
+}
+
+/// Finds the roots of a polynomial, given by its coefficients in increasing powers, in [0, `width`). The roots of
+/// its derivative split the range into pieces on which the polynomial is monotonic, so that each piece holds at most
+/// one root.
+fn polynomial_roots<T: Float>(polynomial: &[T], width: T, tolerance: T) -> Vec<T> {
+    let zero = T::from(0.).expect("Cannot convert to type from f64");
+    let mut breakpoints = vec![zero];
+    if polynomial.len() > 2 {
+        let derivative: Vec<T> = polynomial.iter().enumerate().skip(1)
+            .map(|(power, coefficient)| *coefficient * T::from(power).expect("Cannot convert to type from usize"))
+            .collect();
+        let derivative_tolerance = root_tolerance(&derivative, width, zero);
+        breakpoints.extend(polynomial_roots(&derivative, width, derivative_tolerance).into_iter()
+            .filter(|breakpoint| *breakpoint > zero));
+    }
+    breakpoints.push(width);
+
+    let mut roots = Vec::new();
+    for pair in breakpoints.windows(2) {
+        let lower_value = horner(polynomial, pair[0]);
+        let upper_value = horner(polynomial, pair[1]);
+        if lower_value.abs() <= tolerance {
+            roots.push(pair[0]);
+        } else if upper_value.abs() > tolerance && (lower_value < zero) != (upper_value < zero) {
+            roots.push(bisect(polynomial, pair[0], pair[1], lower_value < zero));
+        }
+    }
+    roots.dedup();
+    roots
+}
+
+/// Bisects a bracketed root of a polynomial until the bracket cannot be split any further.
+fn bisect<T: Float>(polynomial: &[T], mut lower: T, mut upper: T, lower_negative: bool) -> T {
+    let two = T::from(2.).expect("Cannot convert to type from f64");
+    loop {
+        let middle = lower + (upper - lower) / two;
+        if middle <= lower || middle >= upper {
+            return middle;
+        }
+        let value = horner(polynomial, middle);
+        if value == T::from(0.).expect("Cannot convert to type from f64") {
+            return middle;
+        }
+        if (value < T::from(0.).expect("Cannot convert to type from f64")) == lower_negative {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+}
+
+/// Returns the tolerance below which a polynomial value is treated as zero, relative to the magnitude of the
+/// polynomial terms over [0, `width`] and the level it was shifted by.
+fn root_tolerance<T: Float>(polynomial: &[T], width: T, level: T) -> T {
+    let mut power = T::from(1.).expect("Cannot convert to type from f64");
+    let mut scale = level.abs();
+    for coefficient in polynomial {
+        scale = scale + coefficient.abs() * power;
+        power = power * width;
+    }
+    scale * T::from(1000.).expect("Cannot convert to type from f64") * T::epsilon()
+}
+
+fn horner<T: Float>(polynomial: &[T], point: T) -> T {
+    polynomial.iter().rev()
+        .fold(T::from(0.).expect("Cannot convert to type from f64"), |value, coefficient| value * point + *coefficient)
+}
@@ -0,0 +1,162 @@
+use alloc::{string::String, vec, vec::Vec};
+use num_traits::Float;
+use crate::spline_set::{ChannelSmoothing, GcvSplineSet};
+use crate::woltring::support::{check_vector_length, FitDiagnostics, FitMode, FittingError};
+
+/// Represents a smoothed orientation trajectory fitted to unit quaternions, stored as `[w, x, y, z]` with `w` the
+/// scalar part. The quaternions are unwrapped so that consecutive samples lie in the same hemisphere, their four
+/// components are fitted by GCV splines with a shared smoothing parameter, and the fitted quaternions are normalized
+/// again when evaluated. This is accurate as long as the orientation changes by much less than a half turn between
+/// samples.
+///
+/// A quaternion *q* is taken to rotate vectors from the segment frame to the global frame, so that angular velocities
+/// and accelerations are expressed in the global frame. They can be expressed in the segment frame by rotating them
+/// with the conjugate of the orientation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RotationGcvSpline<T: Float> {
+    splines: GcvSplineSet<T>,
+}
+
+impl<T: Float> RotationGcvSpline<T> {
+
+    /// Fits a RotationGcvSpline from user-provided time (knots) and quaternion vectors and a [`FitMode`] selecting how
+    /// the smoothing parameter is chosen. Default values are used for half-order and weights.
+    pub fn from_quaternions(time: &[T], quaternions: &[[T; 4]], mode: FitMode<T>)
+        -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, quaternions, &vec![T::from(1.)
+                                                                .expect("Cannot convert to type from f64");
+                                                            time.len()],
+                                   3, mode)
+    }
+
+    /// Fits a RotationGcvSpline from user-provided time (knots) and quaternion vectors, a vector of weights, a
+    /// half-order, and a [`FitMode`] selecting how the smoothing parameter is chosen. The quaternions are normalized
+    /// before fitting, and must be finite and non-zero.
+    pub fn from_full_parameters(time: &[T], quaternions: &[[T; 4]], weights: &[T], half_order: usize,
+                                mode: FitMode<T>) -> Result<Self, FittingError> {
+        check_vector_length(weights, quaternions.len())?;
+        check_vector_length(time, quaternions.len())?;
+
+        let mut channels: Vec<Vec<T>> = (0..4).map(|_| Vec::with_capacity(quaternions.len())).collect();
+        let mut previous: Option<[T; 4]> = None;
+        for quaternion in quaternions {
+            let norm = dot(quaternion, quaternion).sqrt();
+            if !(norm > T::from(0.).expect("Cannot convert to type from f64") && norm.is_finite()) {
+                return Err(FittingError::InvalidQuaternion(String::from("Quaternions must be finite and non-zero")));
+            }
+            let mut unit = quaternion.map(|component| component / norm);
+            // q and -q describe the same orientation, so keep the one closest to the previous sample
+            if let Some(previous) = previous {
+                if dot(&previous, &unit) < T::from(0.).expect("Cannot convert to type from f64") {
+                    unit = unit.map(|component| -component);
+                }
+            }
+            for (channel, component) in channels.iter_mut().zip(unit) {
+                channel.push(component);
+            }
+            previous = Some(unit);
+        }
+
+        Ok(RotationGcvSpline {
+            splines: GcvSplineSet::from_full_parameters(time, &channels, weights, half_order, mode,
+                                                        ChannelSmoothing::Shared)?,
+        })
+    }
+
+    /// Evaluates the smoothed orientation at a single point, as a unit quaternion.
+    pub fn orientation(&self, point: T) -> [T; 4] {
+        let (unit, _, _) = self.normalized(point, 0);
+        unit
+    }
+
+    /// Evaluates the smoothed orientation at a set of points, as unit quaternions.
+    pub fn orientations(&self, points: &[T]) -> Vec<[T; 4]> {
+        points.iter().map(|point| self.orientation(*point)).collect()
+    }
+
+    /// Evaluates the angular velocity in the global frame at a single point.
+    pub fn angular_velocity(&self, point: T) -> [T; 3] {
+        let (unit, velocity, _) = self.normalized(point, 1);
+        twice_vector_part(&velocity, &unit)
+    }
+
+    /// Evaluates the angular velocity in the global frame at a set of points.
+    pub fn angular_velocities(&self, points: &[T]) -> Vec<[T; 3]> {
+        points.iter().map(|point| self.angular_velocity(*point)).collect()
+    }
+
+    /// Evaluates the angular acceleration in the global frame at a single point.
+    pub fn angular_acceleration(&self, point: T) -> [T; 3] {
+        let (unit, _, acceleration) = self.normalized(point, 2);
+        twice_vector_part(&acceleration, &unit)
+    }
+
+    /// Evaluates the angular acceleration in the global frame at a set of points.
+    pub fn angular_accelerations(&self, points: &[T]) -> Vec<[T; 3]> {
+        points.iter().map(|point| self.angular_acceleration(*point)).collect()
+    }
+
+    /// Returns the statistics of the fit shared by the four quaternion components.
+    pub fn diagnostics(&self) -> FitDiagnostics<T> {
+        self.splines.diagnostics()[0]
+    }
+
+    /// Returns a copy of the time vector used to fit the splines.
+    pub fn time(&self) -> Vec<T> {
+        self.splines.time()
+    }
+
+    /// Returns the normalized quaternion at a point along with its first and second derivatives, up to
+    /// `max_derivative_order`. Derivatives that are not requested are left at 0.
+    fn normalized(&self, point: T, max_derivative_order: usize) -> ([T; 4], [T; 4], [T; 4]) {
+        let zero = [T::from(0.).expect("Cannot convert to type from f64"); 4];
+        let fitted = to_quaternion(&self.splines.single_point(point));
+        let norm = dot(&fitted, &fitted).sqrt();
+        let unit = fitted.map(|component| component / norm);
+        if max_derivative_order == 0 {
+            return (unit, zero, zero);
+        }
+
+        // For p = r * u with r = |p|: u' = (p' - r' u) / r with r' = u . p'
+        let fitted_velocity = to_quaternion(&self.splines.point_derivative(point, 1));
+        let norm_velocity = dot(&unit, &fitted_velocity);
+        let velocity = combine(&fitted_velocity, &unit, norm_velocity, norm);
+        if max_derivative_order == 1 {
+            return (unit, velocity, zero);
+        }
+
+        // u'' = (p'' - r'' u - 2 r' u') / r with r'' = u' . p' + u . p''
+        let fitted_acceleration = to_quaternion(&self.splines.point_derivative(point, 2));
+        let norm_acceleration = dot(&velocity, &fitted_velocity) + dot(&unit, &fitted_acceleration);
+        let two = T::from(2.).expect("Cannot convert to type from f64");
+        let mut acceleration = [T::from(0.).expect("Cannot convert to type from f64"); 4];
+        for (index, component) in acceleration.iter_mut().enumerate() {
+            *component = (fitted_acceleration[index] - norm_acceleration * unit[index]
+                - two * norm_velocity * velocity[index]) / norm;
+        }
+        (unit, velocity, acceleration)
+    }
+}
+
+fn to_quaternion<T: Float>(components: &[T]) -> [T; 4] {
+    [components[0], components[1], components[2], components[3]]
+}
+
+fn dot<T: Float>(a: &[T; 4], b: &[T; 4]) -> T {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2] + a[3] * b[3]
+}
+
+/// Returns (`a` - `scale` * `b`) / `divisor`.
+fn combine<T: Float>(a: &[T; 4], b: &[T; 4], scale: T, divisor: T) -> [T; 4] {
+    [(a[0] - scale * b[0]) / divisor, (a[1] - scale * b[1]) / divisor, (a[2] - scale * b[2]) / divisor,
+     (a[3] - scale * b[3]) / divisor]
+}
+
+/// Returns the vector part of 2 * `a` * conjugate(`b`). For a unit quaternion `b` and `a` its derivative, this is the
+/// angular velocity; for `a` its second derivative, this is the angular acceleration.
+fn twice_vector_part<T: Float>(a: &[T; 4], b: &[T; 4]) -> [T; 3] {
+    let two = T::from(2.).expect("Cannot convert to type from f64");
+    [two * (-a[0] * b[1] + a[1] * b[0] - a[2] * b[3] + a[3] * b[2]),
+     two * (-a[0] * b[2] + a[1] * b[3] + a[2] * b[0] - a[3] * b[1]),
+     two * (-a[0] * b[3] - a[1] * b[2] + a[2] * b[1] + a[3] * b[0])]
+}
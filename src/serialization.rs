@@ -0,0 +1,127 @@
+use alloc::{format, vec::Vec};
+use num_traits::Float;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use crate::extrapolation::Extrapolation;
+use crate::spline::GcvSpline;
+use crate::woltring::support::{check_diagnostics, check_spline_parts, FitDiagnostics, FittingError};
+
+/// Version of the schema in which GCV splines are serialized. It is stored with every serialized spline and checked
+/// when the spline is loaded.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Serialized form of a [`GcvSpline`], borrowing its knots and coefficients.
+#[derive(Serialize)]
+struct GcvSplineRecord<'a, T: Float> {
+    version: u32,
+    knots: &'a [T],
+    coefficients: &'a [T],
+    half_order: usize,
+    extrapolation: Extrapolation,
+    diagnostics: Option<FitDiagnostics<T>>,
+}
+
+impl<'a, T: Float> From<&'a GcvSpline<T>> for GcvSplineRecord<'a, T> {
+    fn from(spline: &'a GcvSpline<T>) -> Self {
+        GcvSplineRecord {
+            version: SCHEMA_VERSION,
+            knots: &spline.knots,
+            coefficients: &spline.coefficients,
+            half_order: spline.half_order,
+            extrapolation: spline.extrapolation,
+            diagnostics: spline.diagnostics,
+        }
+    }
+}
+
+/// Deserialized form of a [`GcvSpline`], which owns its knots and coefficients until they are checked.
+#[derive(Deserialize)]
+struct OwnedGcvSplineRecord<T: Float> {
+    version: u32,
+    knots: Vec<T>,
+    coefficients: Vec<T>,
+    half_order: usize,
+    #[serde(default)]
+    extrapolation: Extrapolation,
+    diagnostics: Option<FitDiagnostics<T>>,
+}
+
+impl<T: Float> TryFrom<OwnedGcvSplineRecord<T>> for GcvSpline<T> {
+    type Error = FittingError;
+
+    fn try_from(record: OwnedGcvSplineRecord<T>) -> Result<Self, FittingError> {
+        check_spline_parts(&record.knots, &record.coefficients, record.half_order)?;
+        let num_knots = record.knots.len();
+        if record.diagnostics.is_some_and(|diagnostics| diagnostics.degrees_of_freedom
+            > T::from(num_knots).expect("Cannot convert to type from usize")) {
+            return Err(FittingError::InvalidDiagnostics(format!(
+                "Degrees of freedom must not exceed the number of knots (time points), {}", num_knots
+            )));
+        }
+        Ok(GcvSpline {
+            knots: record.knots,
+            coefficients: record.coefficients,
+            half_order: record.half_order,
+            diagnostics: record.diagnostics,
+            extrapolation: record.extrapolation,
+        })
+    }
+}
+
+/// Deserialized form of [`FitDiagnostics`], which is checked before it is used.
+#[derive(Deserialize)]
+struct FitDiagnosticsRecord<T: Float> {
+    gcv: T,
+    mean_squared_residual: T,
+    degrees_of_freedom: T,
+    smoothing_ratio: T,
+    mean_squared_error: T,
+    estimated_variance: T,
+    smoothing_parameter: T,
+    evaluations: usize,
+}
+
+impl<T: Float> TryFrom<FitDiagnosticsRecord<T>> for FitDiagnostics<T> {
+    type Error = FittingError;
+
+    fn try_from(record: FitDiagnosticsRecord<T>) -> Result<Self, FittingError> {
+        let diagnostics = FitDiagnostics {
+            gcv: record.gcv,
+            mean_squared_residual: record.mean_squared_residual,
+            degrees_of_freedom: record.degrees_of_freedom,
+            smoothing_ratio: record.smoothing_ratio,
+            mean_squared_error: record.mean_squared_error,
+            estimated_variance: record.estimated_variance,
+            smoothing_parameter: record.smoothing_parameter,
+            evaluations: record.evaluations,
+        };
+        check_diagnostics(&diagnostics)?;
+        Ok(diagnostics)
+    }
+}
+
+impl<T: Float + Serialize> Serialize for GcvSpline<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GcvSplineRecord::from(self).serialize(serializer)
+    }
+}
+
+impl<'de, T: Float + Deserialize<'de>> Deserialize<'de> for GcvSpline<T> {
+    /// Loads a GcvSpline, rejecting unknown schema versions, inconsistent knots and coefficients, and invalid fit
+    /// diagnostics.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let record = OwnedGcvSplineRecord::deserialize(deserializer)?;
+        if record.version != SCHEMA_VERSION {
+            return Err(D::Error::custom(format!("Unsupported GcvSpline schema version {}, expected {}",
+                                                record.version, SCHEMA_VERSION)));
+        }
+        GcvSpline::try_from(record).map_err(D::Error::custom)
+    }
+}
+
+impl<'de, T: Float + Deserialize<'de>> Deserialize<'de> for FitDiagnostics<T> {
+    /// Loads fit diagnostics, rejecting statistics that no fit produces, such as NaN or a negative variance.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        FitDiagnostics::try_from(FitDiagnosticsRecord::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
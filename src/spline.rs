@@ -1,14 +1,20 @@
+use alloc::{vec, vec::Vec};
 use num_traits::Float;
+use crate::extrapolation::Extrapolation;
 use crate::woltring::gcvspl::fit_gcv_spline;
-use crate::woltring::splder::evaluate_spline;
-use crate::woltring::support::FittingError;
+use crate::woltring::search::guess_knot_interval;
+use crate::woltring::splder::{evaluate_spline, evaluate_spline_derivatives};
+use crate::woltring::support::{check_spline_parts, FitDiagnostics, FitMode, FittingError};
 
-/// Represents a GCV spline fitted to provided data.
-#[derive(Clone, Debug, PartialEq)]
+/// Represents a GCV spline fitted to provided data. Two GCV splines are equal if they evaluate the same everywhere,
+/// that is if their knots, coefficients, half-order and extrapolation are equal, regardless of how they were fitted.
+#[derive(Clone, Debug)]
 pub struct GcvSpline<T: Float> {
-    knots: Vec<T>,
-    coefficients: Vec<T>,
-    half_order: usize,
+    pub(crate) knots: Vec<T>,
+    pub(crate) coefficients: Vec<T>,
+    pub(crate) half_order: usize,
+    pub(crate) diagnostics: Option<FitDiagnostics<T>>,
+    pub(crate) extrapolation: Extrapolation,
 }
 
 impl<T: Float> GcvSpline<T> {
@@ -16,7 +22,7 @@ impl<T: Float> GcvSpline<T> {
     /// Fits a GcvSpline from user-provided time (knots) and data vectors. This method uses default values for
     /// half-order, error variance, and weights, which are generally applicable. This method should be used in most
     /// cases.
-    pub fn from_data(time: &Vec<T>, data: &Vec<T>) -> Result<Self, FittingError> {
+    pub fn from_data(time: &[T], data: &[T]) -> Result<Self, FittingError> {
         Self::from_full_parameters(time, data, &vec![T::from(1.)
                                                          .expect("Cannot convert to type from f64"); time.len()],
                                    3, T::from(0.).expect("Cannot convert to type from f64"))
@@ -24,7 +30,7 @@ impl<T: Float> GcvSpline<T> {
 
     /// Fits a GcvSpline from user-provided time (knots) and data vectors and a half-order. A half-order *m* will
     /// produce a GcvSpline with a degree of 2 * *m* - 1.
-    pub fn from_data_and_half_order(time: &Vec<T>, data: &Vec<T>, half_order: usize) -> Result<Self, FittingError> {
+    pub fn from_data_and_half_order(time: &[T], data: &[T], half_order: usize) -> Result<Self, FittingError> {
         Self::from_full_parameters(time, data, &vec![T::from(1.)
                                                          .expect("Cannot convert to type from f64"); time.len()],
                                    half_order, T::from(0.).expect("Cannot convert to type from f64"))
@@ -34,7 +40,7 @@ impl<T: Float> GcvSpline<T> {
     /// Increasing the provided error variance will introduce smoothing into the spline. With higher error variance
     /// values, input data points will not be matched as closely. Generally, it is recommended to low-pass filter noisy
     /// input data before fitting with an error variance of 0 rather than using this type of smoothing.
-    pub fn from_data_half_order_and_smoothing(time: &Vec<T>, data: &Vec<T>, half_order: usize, error_variance: T)
+    pub fn from_data_half_order_and_smoothing(time: &[T], data: &[T], half_order: usize, error_variance: T)
         -> Result<Self, FittingError> {
         Self::from_full_parameters(time, data, &vec![T::from(1.)
                                                          .expect("Cannot convert to type from f64"); time.len()],
@@ -42,24 +48,80 @@ impl<T: Float> GcvSpline<T> {
     }
 
     /// Fits a GcvSpline from user-provided time (knots) and data vectors, a half-order, an error variance, and a
-    /// vector of weights. The weights define how important individual fitting points are.
-    pub fn from_full_parameters(time: &Vec<T>, data: &Vec<T>, weights: &Vec<T>, half_order: usize,
+    /// vector of weights. The weights define how important individual fitting points are. An error variance of 0
+    /// interpolates the data, a positive error variance is treated as the known variance of the data
+    /// ([`FitMode::KnownVariance`]), and a negative error variance selects generalized cross-validation
+    /// ([`FitMode::Gcv`]).
+    pub fn from_full_parameters(time: &[T], data: &[T], weights: &[T], half_order: usize,
                                 error_variance: T) -> Result<Self, FittingError> {
-        let coefficients = fit_gcv_spline(time, data, weights, half_order, error_variance)?;
+        let mode = if error_variance == T::from(0.).expect("Cannot convert to type from f64") {
+            FitMode::Interpolate
+        } else if error_variance < T::from(0.).expect("Cannot convert to type from f64") {
+            FitMode::Gcv
+        } else {
+            FitMode::KnownVariance(error_variance)
+        };
+        Self::from_full_parameters_and_mode(time, data, weights, half_order, mode)
+    }
+
+    /// Fits a GcvSpline from user-provided time (knots) and data vectors and a [`FitMode`] selecting how the
+    /// smoothing parameter is chosen. Default values are used for half-order and weights.
+    pub fn from_data_and_mode(time: &[T], data: &[T], mode: FitMode<T>) -> Result<Self, FittingError> {
+        Self::from_full_parameters_and_mode(time, data, &vec![T::from(1.)
+                                                                  .expect("Cannot convert to type from f64");
+                                                              time.len()],
+                                            3, mode)
+    }
+
+    /// Fits a GcvSpline from user-provided time (knots) and data vectors, a half-order, a vector of weights, and a
+    /// [`FitMode`] selecting how the smoothing parameter is chosen.
+    pub fn from_full_parameters_and_mode(time: &[T], data: &[T], weights: &[T], half_order: usize,
+                                         mode: FitMode<T>) -> Result<Self, FittingError> {
+        let (coefficients, diagnostics) = fit_gcv_spline(time, data, weights, half_order, mode)?;
         Ok(GcvSpline {
-            knots: time.clone(),
+            knots: time.to_vec(),
             coefficients,
-            half_order
+            half_order,
+            diagnostics: Some(diagnostics),
+            extrapolation: Extrapolation::default(),
         })
     }
 
+    /// Creates a GcvSpline from known knots, B-spline coefficients, and a half-order, such as those of a spline fitted
+    /// by Woltring's GCVSPL or stored elsewhere. The knots must be strictly increasing, with one coefficient per knot
+    /// and at least 2 * *m* knots for a half-order *m*. The spline does not describe any data, so it has no
+    /// diagnostics.
+    pub fn from_parts(knots: &[T], coefficients: &[T], half_order: usize) -> Result<Self, FittingError> {
+        check_spline_parts(knots, coefficients, half_order)?;
+        Ok(GcvSpline {
+            knots: knots.to_vec(),
+            coefficients: coefficients.to_vec(),
+            half_order,
+            diagnostics: None,
+            extrapolation: Extrapolation::default(),
+        })
+    }
+
+    pub(crate) fn from_fitted_parts(knots: Vec<T>, coefficients: Vec<T>, half_order: usize,
+                                    diagnostics: FitDiagnostics<T>) -> Self {
+        GcvSpline {
+            knots,
+            coefficients,
+            half_order,
+            diagnostics: Some(diagnostics),
+            extrapolation: Extrapolation::default(),
+        }
+    }
+
     /// Creates a GcvSpline with default values. This does not describe any user-provided data.
     pub fn new() -> Self {
         GcvSpline {
             knots: vec![T::from(0.).expect("Cannot convert to type from f64"),
                         T::from(1.).expect("Cannot convert to type from f64")],
             coefficients: vec![T::from(0.).expect("Cannot convert to type from f64"); 2],
-            half_order: 1
+            half_order: 1,
+            diagnostics: None,
+            extrapolation: Extrapolation::default(),
         }
     }
 
@@ -69,38 +131,95 @@ impl<T: Float> GcvSpline<T> {
     }
 
     /// Evaluates a GCV spline at a set of points.
-    pub fn points(&self, points: &Vec<T>) -> Vec<T> {
-        points.iter().map(|point| self.single_point(*point)).collect()
+    pub fn points(&self, points: &[T]) -> Vec<T> {
+        self.derivative(points, 0)
     }
 
-    /// Evaluates a derivative of a given order at a single point.
+    /// Evaluates a derivative of a given order at a single point. Points outside the knots are handled according to
+    /// the [`Extrapolation`] of the spline.
     pub fn point_derivative(&self, point: T, derivative_order: usize) -> T {
-        let end = self.knots.last().expect("Time cannot be empty");
-        let start = self.knots.first().expect("Time cannot be empty");
-        let knot_guess = ((point - *start) / (end.clone() - start.clone()) * T::from(self.knots.len())
-            .expect("Cannot convert to type from usize"))
-            .to_usize().expect("Cannot convert from type to usize");
+        self.extrapolated_derivative(point, derivative_order)
+            .unwrap_or_else(|| self.evaluate_piecewise(point, derivative_order))
+    }
+
+    /// Evaluates a derivative of a given order of the polynomial pieces of the spline, which continue past the knots.
+    pub(crate) fn evaluate_piecewise(&self, point: T, derivative_order: usize) -> T {
+        let knot_guess = guess_knot_interval(&self.knots, point);
 
         evaluate_spline(derivative_order, self.half_order, point, &self.knots, &self.coefficients, knot_guess)
     }
 
-    /// Evaluates a derivative of a given order at a set of points.
-    pub fn derivative(&self, points: &Vec<T>, derivative_order: usize) -> Vec<T> {
+    /// Evaluates a derivative of a given order at a set of points. Points sorted in increasing order are evaluated
+    /// with a [`SplineCursor`](crate::SplineCursor), which avoids searching for the knot interval of each point.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<T> {
+        if points.windows(2).all(|pair| pair[0] <= pair[1]) {
+            return self.sorted_derivative(points, derivative_order);
+        }
         points.iter().map(|point| self.point_derivative(*point, derivative_order)).collect()
     }
 
+    /// Evaluates a GCV spline at each point yielded by `points`, writing the values into `output` in the same order.
+    /// Returns the number of values written; points beyond the length of `output` are not evaluated.
+    pub fn points_into<I: IntoIterator<Item = T>>(&self, points: I, output: &mut [T]) -> usize {
+        self.derivative_into(points, 0, output)
+    }
+
+    /// Evaluates a derivative of a given order at each point yielded by `points`, writing the values into `output` in
+    /// the same order. Returns the number of values written; points beyond the length of `output` are not evaluated.
+    /// Points are evaluated with a [`SplineCursor`](crate::SplineCursor), so sorted points are evaluated fastest.
+    pub fn derivative_into<I: IntoIterator<Item = T>>(&self, points: I, derivative_order: usize, output: &mut [T])
+        -> usize {
+        let mut cursor = self.cursor();
+        let mut written = 0;
+        for (value, point) in output.iter_mut().zip(points) {
+            *value = cursor.point_derivative(point, derivative_order);
+            written += 1;
+        }
+        written
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` at a single point, in increasing order of
+    /// derivative. This searches for the knot interval of the point only once and reuses the differences of the
+    /// B-spline coefficients between derivatives, so it is cheaper than separate calls to
+    /// [`GcvSpline::point_derivative`].
+    pub fn evaluate_all_derivatives(&self, point: T, max_order: usize) -> Vec<T> {
+        let mut values = vec![T::from(0.).expect("Cannot convert to type from f64"); max_order + 1];
+        if self.extrapolated_derivative(point, 0).is_some() {
+            for (derivative_order, value) in values.iter_mut().enumerate() {
+                *value = self.point_derivative(point, derivative_order);
+            }
+        } else {
+            evaluate_spline_derivatives(self.half_order, point, &self.knots, &self.coefficients,
+                                        guess_knot_interval(&self.knots, point), &mut values);
+        }
+        values
+    }
+
+    /// Evaluates the value and all derivatives up to `max_order` at a set of points. The result holds one vector per
+    /// derivative order, starting with the values.
+    pub fn all_derivatives(&self, points: &[T], max_order: usize) -> Vec<Vec<T>> {
+        let mut evaluated = vec![Vec::with_capacity(points.len()); max_order + 1];
+        let mut cursor = self.cursor();
+        for point in points {
+            for (derivative, value) in evaluated.iter_mut().zip(cursor.evaluate_all_derivatives(*point, max_order)) {
+                derivative.push(value);
+            }
+        }
+        evaluated
+    }
+
     /// Evaluates the first derivative at a set of points.
-    pub fn first_derivative(&self, points: &Vec<T>) -> Vec<T> {
+    pub fn first_derivative(&self, points: &[T]) -> Vec<T> {
         self.derivative(points, 1)
     }
 
     /// Evaluates the second derivative at a set of points.
-    pub fn second_derivative(&self, points: &Vec<T>) -> Vec<T> {
+    pub fn second_derivative(&self, points: &[T]) -> Vec<T> {
         self.derivative(points, 2)
     }
 
     /// Evaluates the third derivative at a set of points.
-    pub fn third_derivative(&self, points: &Vec<T>) -> Vec<T> {
+    pub fn third_derivative(&self, points: &[T]) -> Vec<T> {
         self.derivative(points, 3)
     }
 
@@ -113,6 +232,29 @@ impl<T: Float> GcvSpline<T> {
     pub fn knots(&self) -> Vec<T> {
         self.time()
     }
+
+    /// Returns a copy of the B-spline coefficients of the GCV spline, one per knot.
+    pub fn coefficients(&self) -> Vec<T> {
+        self.coefficients.clone()
+    }
+
+    /// Returns the half-order of the GCV spline. A half-order *m* corresponds to a degree of 2 * *m* - 1.
+    pub fn half_order(&self) -> usize {
+        self.half_order
+    }
+
+    /// Returns the statistics of the fit that produced the GCV spline, or `None` if the spline was not fitted to
+    /// data.
+    pub fn diagnostics(&self) -> Option<FitDiagnostics<T>> {
+        self.diagnostics
+    }
+}
+
+impl<T: Float> PartialEq for GcvSpline<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.knots == other.knots && self.coefficients == other.coefficients && self.half_order == other.half_order
+            && self.extrapolation == other.extrapolation
+    }
 }
 
 impl<T: Float> Default for GcvSpline<T> {
@@ -0,0 +1,136 @@
+use alloc::{string::String, vec, vec::Vec};
+use num_traits::Float;
+use crate::spline::GcvSpline;
+use crate::woltring::gcvspl::DesignMatrices;
+use crate::woltring::search::guess_knot_interval;
+use crate::woltring::splder::evaluate_spline;
+use crate::woltring::support::{check_vector_length, FitDiagnostics, FitMode, FittingError};
+
+/// Selects how the smoothing parameter is chosen for the channels of a [`GcvSplineSet`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelSmoothing {
+    /// Chooses one smoothing parameter for all channels, pooling their residuals as in Woltring's GCVSPL.
+    Shared,
+    /// Chooses a smoothing parameter for each channel separately.
+    Independent,
+}
+
+/// Represents a set of GCV splines fitted to several data channels sampled at the same time points. The B-spline
+/// basis and weighted matrices are computed once and shared by all channels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GcvSplineSet<T: Float> {
+    pub(crate) knots: Vec<T>,
+    coefficients: Vec<Vec<T>>,
+    half_order: usize,
+    diagnostics: Vec<FitDiagnostics<T>>,
+}
+
+impl<T: Float> GcvSplineSet<T> {
+
+    /// Fits a GcvSplineSet from a user-provided time (knots) vector and one data vector per channel. Default values
+    /// are used for half-order and weights.
+    pub fn from_channels<C: AsRef<[T]>>(time: &[T], channels: &[C], mode: FitMode<T>, smoothing: ChannelSmoothing)
+        -> Result<Self, FittingError> {
+        Self::from_full_parameters(time, channels, &vec![T::from(1.)
+                                                             .expect("Cannot convert to type from f64");
+                                                         time.len()],
+                                   3, mode, smoothing)
+    }
+
+    /// Fits a GcvSplineSet from a user-provided time (knots) vector, one data vector per channel, a vector of
+    /// weights shared by all channels, and a half-order. `mode` selects how the smoothing parameter is chosen, and
+    /// `smoothing` whether it is shared between channels.
+    pub fn from_full_parameters<C: AsRef<[T]>>(time: &[T], channels: &[C], weights: &[T], half_order: usize,
+                                mode: FitMode<T>, smoothing: ChannelSmoothing) -> Result<Self, FittingError> {
+        if channels.is_empty() {
+            return Err(FittingError::VectorLengthMismatch(String::from("At least one channel is needed")));
+        }
+        for channel in channels {
+            check_vector_length(channel.as_ref(), time.len())?;
+        }
+
+        let design = DesignMatrices::new(time, weights, half_order)?;
+        let (coefficients, diagnostics) = match smoothing {
+            ChannelSmoothing::Shared => {
+                let data: Vec<T> = channels.iter().flat_map(|channel| channel.as_ref()).copied().collect();
+                let (coefficients, diagnostics) = design.fit(&data, mode)?;
+                (coefficients.chunks(time.len()).map(|channel| channel.to_vec()).collect(),
+                 vec![diagnostics; channels.len()])
+            }
+            ChannelSmoothing::Independent => {
+                channels.iter().map(|channel| design.fit(channel.as_ref(), mode)).collect::<Result<Vec<_>, _>>()?
+                    .into_iter().unzip()
+            }
+        };
+
+        Ok(GcvSplineSet {
+            knots: time.to_vec(),
+            coefficients,
+            half_order,
+            diagnostics,
+        })
+    }
+
+    /// Returns the number of channels in the set.
+    pub fn num_channels(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Returns the half-order of the GCV splines.
+    pub(crate) fn half_order(&self) -> usize {
+        self.half_order
+    }
+
+    /// Returns a GcvSpline describing a single channel, or `None` if the channel does not exist.
+    pub fn channel(&self, index: usize) -> Option<GcvSpline<T>> {
+        Some(GcvSpline::from_fitted_parts(self.knots.clone(), self.coefficients.get(index)?.clone(),
+                                          self.half_order, self.diagnostics[index]))
+    }
+
+    /// Evaluates all channels at a single point.
+    pub fn single_point(&self, point: T) -> Vec<T> {
+        self.point_derivative(point, 0)
+    }
+
+    /// Evaluates all channels at a set of points. The result holds one vector per channel.
+    pub fn points(&self, points: &[T]) -> Vec<Vec<T>> {
+        self.derivative(points, 0)
+    }
+
+    /// Evaluates a derivative of a given order of all channels at a single point.
+    pub fn point_derivative(&self, point: T, derivative_order: usize) -> Vec<T> {
+        let knot_guess = guess_knot_interval(&self.knots, point);
+        self.coefficients.iter()
+            .map(|coefficients| evaluate_spline(derivative_order, self.half_order, point, &self.knots,
+                                                coefficients, knot_guess))
+            .collect()
+    }
+
+    /// Evaluates a derivative of a given order of all channels at a set of points. The result holds one vector per
+    /// channel.
+    pub fn derivative(&self, points: &[T], derivative_order: usize) -> Vec<Vec<T>> {
+        let mut evaluated = vec![Vec::with_capacity(points.len()); self.num_channels()];
+        for point in points {
+            for (channel, value) in evaluated.iter_mut().zip(self.point_derivative(*point, derivative_order)) {
+                channel.push(value);
+            }
+        }
+        evaluated
+    }
+
+    /// Returns the statistics of the fit of each channel. With [`ChannelSmoothing::Shared`], all channels report the
+    /// statistics of the pooled fit.
+    pub fn diagnostics(&self) -> Vec<FitDiagnostics<T>> {
+        self.diagnostics.clone()
+    }
+
+    /// Returns a copy of the time vector used to fit the GCV splines.
+    pub fn time(&self) -> Vec<T> {
+        self.knots.clone()
+    }
+
+    /// Returns a copy of the knots vector used to fit the GCV splines.
+    pub fn knots(&self) -> Vec<T> {
+        self.time()
+    }
+}
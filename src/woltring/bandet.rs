@@ -1,14 +1,19 @@
+use alloc::format;
 use num_traits::Float;
 use crate::woltring::support::{check_order, FittingError};
 
-pub(crate) fn consume_and_decompose<T: Float>(mut matrix: Vec<T>, half_order: usize)
-    -> Result<Vec<T>, FittingError> {
+/// Decomposes a banded matrix in place into LU factors without pivoting. Returns
+/// [`FittingError::SingularSystem`] if a pivot is zero, not finite, or negligible relative to the largest entry of
+/// the matrix.
+pub(crate) fn decompose_in_place<T: Float>(matrix: &mut [T], half_order: usize) -> Result<(), FittingError> {
     let num_knots = matrix.len() / (2 * half_order + 1);
     check_order(half_order, num_knots)?;
+    let threshold = matrix.iter().fold(T::from(0.).expect("Cannot convert to type from f64"),
+                                       |largest, value| largest.max(value.abs())) * T::epsilon() * T::epsilon();
 
     for knot_index in 1 ..= num_knots {
         let mut decomp_inner = matrix[(knot_index - 1) * (half_order * 2 + 1) + half_order];
-        let order_index = std::cmp::min(half_order, knot_index - 1);
+        let order_index = core::cmp::min(half_order, knot_index - 1);
 
         if order_index >= 1 {
             for idx in 1 ..= order_index {
@@ -17,14 +22,17 @@ pub(crate) fn consume_and_decompose<T: Float>(mut matrix: Vec<T>, half_order: us
             }
             matrix[(knot_index - 1) * (half_order * 2 + 1) + half_order] = decomp_inner;
         }
+        if !(decomp_inner.abs() > threshold && decomp_inner.is_finite()) {
+            return Err(FittingError::SingularSystem(format!("Pivot {} of the fitting system is singular", knot_index)));
+        }
 
-        let outer_limit = std::cmp::min(half_order, num_knots - knot_index);
+        let outer_limit = core::cmp::min(half_order, num_knots - knot_index);
 
         if outer_limit >= 1 {
             for outer in 1 ..= outer_limit {
                 let mut decomp_lower = matrix[(knot_index + outer - 1) * (half_order * 2 + 1) - outer + half_order];
 
-                let inner_limit = std::cmp::min(half_order - outer, knot_index - 1);
+                let inner_limit = core::cmp::min(half_order - outer, knot_index - 1);
 
                 if inner_limit >= 1 {
                     let mut decomp_upper = matrix[(knot_index - 1) * (half_order * 2 + 1) + outer + half_order];
@@ -44,5 +52,5 @@ pub(crate) fn consume_and_decompose<T: Float>(mut matrix: Vec<T>, half_order: us
         }
     }
 
-    Ok(matrix)
+    Ok(())
 }
\ No newline at end of file
@@ -1,11 +1,12 @@
 use num_traits::Float;
-use crate::woltring::support::{check_order, FittingError};
+use crate::woltring::support::{check_order, check_vector_length, FittingError};
 
-pub(crate) fn solve_decomposed_system<T: Float>(matrix: &Vec<T>, rhs: &Vec<T>, half_order: usize)
-    -> Result<Vec<T>, FittingError> {
+pub(crate) fn solve_decomposed_system<T: Float>(matrix: &[T], rhs: &[T], half_order: usize, solution: &mut [T])
+    -> Result<(), FittingError> {
     let num_knots = matrix.len() / (2 * half_order + 1);
     check_order(half_order, num_knots)?;
-    let mut solution = vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots];
+    check_vector_length(rhs, num_knots)?;
+    check_vector_length(solution, num_knots)?;
 
     if half_order == 0 {
         for idx in 1 ..= num_knots {
@@ -14,7 +15,7 @@ pub(crate) fn solve_decomposed_system<T: Float>(matrix: &Vec<T>, rhs: &Vec<T>, h
     } else {
         solution[0] = rhs[0];
         for outer in 2 ..= num_knots {
-            let inner_limit = std::cmp::min(half_order, outer - 1);
+            let inner_limit = core::cmp::min(half_order, outer - 1);
             let mut solution_point = rhs[outer - 1];
             for inner in 1 ..= inner_limit {
                 solution_point = solution_point - matrix[(outer - 1) * (half_order * 2 + 1) - inner + half_order] *
@@ -25,7 +26,7 @@ pub(crate) fn solve_decomposed_system<T: Float>(matrix: &Vec<T>, rhs: &Vec<T>, h
 
         solution[num_knots - 1] = solution[num_knots - 1] / matrix[(num_knots - 1) * (half_order * 2 + 1) + half_order];
         for outer in (1 .. num_knots).rev() {
-            let inner_limit = std::cmp::min(half_order, num_knots - outer);
+            let inner_limit = core::cmp::min(half_order, num_knots - outer);
             let mut solution_point = solution[outer - 1];
             for inner in 1 ..= inner_limit {
                 solution_point = solution_point - matrix[(outer - 1) * (half_order * 2 + 1) + inner + half_order] *
@@ -35,5 +36,39 @@ pub(crate) fn solve_decomposed_system<T: Float>(matrix: &Vec<T>, rhs: &Vec<T>, h
         }
     }
 
-    Ok(solution)
-}
\ No newline at end of file
+    Ok(())
+}
+
+/// Solves the transposed system matrix**T * solution = rhs using the LU decomposition of matrix computed by
+/// `decompose_in_place`.
+pub(crate) fn solve_transposed_system<T: Float>(matrix: &[T], rhs: &[T], half_order: usize, solution: &mut [T])
+    -> Result<(), FittingError> {
+    let num_knots = matrix.len() / (2 * half_order + 1);
+    check_order(half_order, num_knots)?;
+    check_vector_length(rhs, num_knots)?;
+    check_vector_length(solution, num_knots)?;
+
+    // Forward substitution with U**T
+    for outer in 1 ..= num_knots {
+        let inner_limit = core::cmp::min(half_order, outer - 1);
+        let mut solution_point = rhs[outer - 1];
+        for inner in 1 ..= inner_limit {
+            solution_point = solution_point - matrix[(outer - inner - 1) * (half_order * 2 + 1) + inner + half_order] *
+                solution[outer - inner - 1];
+        }
+        solution[outer - 1] = solution_point / matrix[(outer - 1) * (half_order * 2 + 1) + half_order];
+    }
+
+    // Back substitution with L**T, which has a unit diagonal
+    for outer in (1 .. num_knots).rev() {
+        let inner_limit = core::cmp::min(half_order, num_knots - outer);
+        let mut solution_point = solution[outer - 1];
+        for inner in 1 ..= inner_limit {
+            solution_point = solution_point - matrix[(outer + inner - 1) * (half_order * 2 + 1) - inner + half_order] *
+                solution[outer + inner - 1];
+        }
+        solution[outer - 1] = solution_point;
+    }
+
+    Ok(())
+}
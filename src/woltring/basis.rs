@@ -1,26 +1,39 @@
+use alloc::{vec, vec::Vec};
 use num_traits::Float;
-use crate::woltring::support::{check_increasing, check_order, FittingError};
+use crate::woltring::support::{check_increasing, check_order, check_workspace, FittingError};
 
-pub(crate) fn create_basis<T: Float>(half_order: usize, knots: &Vec<T>)
+pub(crate) fn create_basis<T: Float>(half_order: usize, knots: &[T])
     -> Result<(Vec<T>, T), FittingError> {
+    check_order(half_order, knots.len())?;
+    let mut spline_tableau =
+        vec![T::from(0.).expect("Cannot convert to type from f64"); knots.len() * (half_order * 2 - 1)];
+    let mut working_vec = vec![T::from(0.).expect("Cannot convert to type from f64"); 2 * half_order];
+    let basis_l1_norm = create_basis_into(half_order, knots, &mut spline_tableau, &mut working_vec)?;
+    Ok((spline_tableau, basis_l1_norm))
+}
+
+/// Computes the B-spline tableau like `create_basis`, writing it into `spline_tableau` of length
+/// `knots.len()` * (2 * `half_order` - 1) and using `working_vec` of length 2 * `half_order` as working storage.
+/// Returns the L1 norm of the tableau.
+pub(crate) fn create_basis_into<T: Float>(half_order: usize, knots: &[T], spline_tableau: &mut [T],
+                                          working_vec: &mut [T]) -> Result<T, FittingError> {
     let num_knots = knots.len();
-    check_increasing(knots)?;
     check_order(half_order, num_knots)?;
+    check_increasing(knots)?;
 
     let spline_order = half_order * 2 - 1;
+    check_workspace(spline_tableau, num_knots * spline_order)?;
+    check_workspace(working_vec, 2 * half_order)?;
 
     // Linear case (half order and spline order = 1)
     if half_order == 1 {
-        return Ok((vec![T::from(1.).expect("Cannot convert to type from f64"); num_knots],
-                   T::from(1.).expect("Cannot convert to type from f64")));
+        spline_tableau[.. num_knots].fill(T::from(1.).expect("Cannot convert to type from f64"));
+        return Ok(T::from(1.).expect("Cannot convert to type from f64"));
     }
 
-    let mut spline_tableau =
-        vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots * spline_order];
     // General case
     for knot_index in 1 ..= num_knots {
-        let mut working_vec =
-            vec![T::from(0.).expect("Cannot convert to type from f64"); 2 * half_order];
+        working_vec.fill(T::from(0.).expect("Cannot convert to type from f64"));
 
         // First row
         if knot_index != 1 && knot_index != num_knots {
@@ -47,12 +60,11 @@ pub(crate) fn create_basis<T: Float>(half_order: usize, knots: &Vec<T>)
                 }
             }
 
-            let lower_bound: i32;
-            if knot_index as i32 - tableau_index as i32 + 1 < 0 {
-                lower_bound = 1;
+            let lower_bound: i32 = if knot_index as i32 - tableau_index as i32 + 1 < 0 {
+                1
             } else {
-                lower_bound = (knot_index as i32 - tableau_index as i32 + 1).max(1);
-            }
+                (knot_index as i32 - tableau_index as i32 + 1).max(1)
+            };
             let upper_bound = (knot_index - 1).min(num_knots - tableau_index);
             if lower_bound <= upper_bound as i32 {
                 // Ordinary splines
@@ -94,15 +106,11 @@ pub(crate) fn create_basis<T: Float>(half_order: usize, knots: &Vec<T>)
         }
     }
 
-    let abs_vec = spline_tableau.iter().map(|element| element.abs()).collect::<Vec<T>>();
-    let mut abs_iter = abs_vec.iter();
-
     let mut basis_l1_norm = T::from(0.).expect("Cannot convert to type from f64");
-
-    while let Some(item) = abs_iter.next() {
-        basis_l1_norm = basis_l1_norm + item.clone();
+    for item in spline_tableau[.. num_knots * spline_order].iter() {
+        basis_l1_norm = basis_l1_norm + item.abs();
     }
     basis_l1_norm = basis_l1_norm / T::from(num_knots).expect("Cannot convert to usize from type");
 
-    Ok((spline_tableau, basis_l1_norm))
-}
\ No newline at end of file
+    Ok(basis_l1_norm)
+}
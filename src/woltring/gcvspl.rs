@@ -1,143 +1,265 @@
+use alloc::{vec, vec::Vec};
 use num_traits::Float;
-use crate::woltring::basis::create_basis;
-use crate::woltring::prep::create_weighted_matrix;
-use crate::woltring::splc::fit_spline_coefficients_with_stats;
-use crate::woltring::support::{check_increasing, check_order, check_vector_length, FittingError};
-
-pub(crate) fn fit_gcv_spline<T: Float>(knots: &Vec<T>, data: &Vec<T>, weight_factors: &Vec<T>,
-                      half_order: usize, error_variance: T)
-        -> Result<Vec<T>, FittingError> {
+use crate::woltring::basis::{create_basis, create_basis_into};
+use crate::woltring::prep::{create_weighted_matrix, create_weighted_matrix_into};
+use crate::woltring::bandet::decompose_in_place;
+use crate::woltring::splc::{assemble_system, effective_smoothing, fit_spline_coefficients_with_stats,
+                            influence_diagonal};
+use crate::woltring::trinv::trace_inverse;
+use crate::woltring::support::{check_channel_length, check_fit_mode, check_increasing, check_order,
+                               check_vector_length, check_workspace, FitDiagnostics, FitMode, FittingError};
+
+pub(crate) fn fit_gcv_spline<T: Float>(knots: &[T], data: &[T], weight_factors: &[T],
+                      half_order: usize, mode: FitMode<T>)
+        -> Result<(Vec<T>, FitDiagnostics<T>), FittingError> {
+    DesignMatrices::new(knots, weight_factors, half_order)?.fit(data, mode)
+}
+
+/// Holds the B-spline basis and weighted penalty matrices for a fixed set of knots, weights and half-order, so that
+/// any number of data channels can be fitted against them.
+#[derive(Clone, Debug)]
+pub(crate) struct DesignMatrices<T: Float> {
+    half_order: usize,
+    weight_factors: Vec<T>,
+    spline_tableau: Vec<T>,
+    weighted_matrix: Vec<T>,
+    weighted_matrix_norm: T,
+}
+
+impl<T: Float> DesignMatrices<T> {
+    pub(crate) fn new(knots: &[T], weight_factors: &[T], half_order: usize) -> Result<Self, FittingError> {
+        let num_knots = knots.len();
+        check_order(half_order, num_knots)?;
+        check_increasing(knots)?;
+        check_vector_length(weight_factors, num_knots)?;
+
+        // Compute design matrices and norms
+        let (spline_tableau, basis_l1_norm): (Vec<T>, T) = create_basis(half_order, knots)?;
+        let (weighted_matrix, weighted_matrix_norm): (Vec<T>, T)
+            = create_weighted_matrix(half_order, knots, weight_factors)?;
+
+        Ok(DesignMatrices {
+            half_order,
+            weight_factors: weight_factors.to_vec(),
+            spline_tableau,
+            weighted_matrix,
+            weighted_matrix_norm: weighted_matrix_norm / basis_l1_norm,
+        })
+    }
+
+    pub(crate) fn num_knots(&self) -> usize {
+        self.weight_factors.len()
+    }
+
+    pub(crate) fn half_order(&self) -> usize {
+        self.half_order
+    }
+
+    /// Returns the length of the banded working matrix needed by `fit_into`.
+    pub(crate) fn band_matrix_len(&self) -> usize {
+        self.weighted_matrix.len()
+    }
+
+    /// Returns the LU decomposition of the banded fitting system at a smoothing parameter.
+    pub(crate) fn decomposed_system(&self, smoothing: T) -> Result<Vec<T>, FittingError> {
+        let mut band_matrix = vec![T::from(0.).expect("Cannot convert to type from f64"); self.band_matrix_len()];
+        assemble_system(self.half_order, smoothing, smoothing_tolerance(), &self.spline_tableau,
+                        &self.weighted_matrix, self.weighted_matrix_norm, &mut band_matrix);
+        decompose_in_place(&mut band_matrix, self.half_order)?;
+        Ok(band_matrix)
+    }
+
+    /// Returns the diagonal of the influence matrix at a smoothing parameter, i.e. the leverage of each data point,
+    /// and the degrees of freedom TRACE[I - influence matrix], computed as in the fit.
+    pub(crate) fn leverage(&self, smoothing: T) -> Result<(Vec<T>, T), FittingError> {
+        let mut band_matrix = self.decomposed_system(smoothing)?;
+        let trace = trace_inverse(&self.weighted_matrix, &mut band_matrix, self.half_order)?;
+        let mut leverage = vec![T::from(0.).expect("Cannot convert to type from f64"); self.num_knots()];
+        influence_diagonal(self.half_order, &self.spline_tableau, &band_matrix, &mut leverage)?;
+        let smoothing = effective_smoothing(smoothing, smoothing_tolerance(), self.weighted_matrix_norm);
+        Ok((leverage, smoothing * trace))
+    }
+
+    /// Fits one or more data channels, stored one after another in `data`, with a single smoothing parameter chosen
+    /// according to `mode`. Returns the coefficients of all channels, stored in the same layout as `data`.
+    pub(crate) fn fit(&self, data: &[T], mode: FitMode<T>) -> Result<(Vec<T>, FitDiagnostics<T>), FittingError> {
+        let mut coefficients = vec![T::from(0.).expect("Cannot convert to type from f64"); data.len()];
+        let mut band_matrix = vec![T::from(0.).expect("Cannot convert to type from f64"); self.band_matrix_len()];
+        let mut stats = [T::from(0.).expect("Cannot convert to type from f64"); 6];
+        let diagnostics = self.fit_into(data, mode, &mut coefficients, &mut band_matrix, &mut stats)?;
+        Ok((coefficients, diagnostics))
+    }
+
+    /// Fits like `fit`, writing the coefficients into `coefficients` and using `band_matrix` and `stats` as working
+    /// storage instead of allocating.
+    pub(crate) fn fit_into(&self, data: &[T], mode: FitMode<T>, coefficients: &mut [T], band_matrix: &mut [T],
+                           stats: &mut [T]) -> Result<FitDiagnostics<T>, FittingError> {
+        fit_design(self.half_order, &self.weight_factors, &self.spline_tableau, &self.weighted_matrix,
+                   self.weighted_matrix_norm, data, mode, coefficients, band_matrix, stats)
+    }
+}
+
+/// Returns the length of the workspace needed by `fit_gcv_spline_into`.
+pub(crate) fn workspace_len(num_knots: usize, half_order: usize) -> usize {
+    num_knots * (2 * half_order).saturating_sub(1) + 2 * num_knots * (2 * half_order + 1) + 2 * half_order
+}
+
+/// Fits like `fit_gcv_spline`, writing the coefficients into `coefficients` and using `workspace`, of at least
+/// `workspace_len` elements, for the design matrices and all working storage instead of allocating.
+pub(crate) fn fit_gcv_spline_into<T: Float>(knots: &[T], data: &[T], weight_factors: &[T], half_order: usize,
+                                            mode: FitMode<T>, coefficients: &mut [T], workspace: &mut [T])
+    -> Result<FitDiagnostics<T>, FittingError> {
     let num_knots = knots.len();
     check_order(half_order, num_knots)?;
-    check_increasing(knots)?;
     check_vector_length(data, num_knots)?;
-    check_vector_length(weight_factors, num_knots)?;
+    check_vector_length(coefficients, num_knots)?;
+    check_workspace(workspace, workspace_len(num_knots, half_order))?;
+
+    let (spline_tableau, workspace) = workspace.split_at_mut(num_knots * (2 * half_order - 1));
+    let (weighted_matrix, workspace) = workspace.split_at_mut(num_knots * (2 * half_order + 1));
+    let (band_matrix, workspace) = workspace.split_at_mut(num_knots * (2 * half_order + 1));
+    let basis_l1_norm = create_basis_into(half_order, knots, spline_tableau, &mut workspace[.. 2 * half_order])?;
+    let weighted_matrix_norm = create_weighted_matrix_into(half_order, knots, weight_factors, weighted_matrix)?;
+
+    let mut stats = [T::from(0.).expect("Cannot convert to type from f64"); 6];
+    fit_design(half_order, weight_factors, spline_tableau, weighted_matrix, weighted_matrix_norm / basis_l1_norm,
+               data, mode, coefficients, band_matrix, &mut stats)
+}
+
+/// Fits one or more data channels against borrowed design matrices, as described by `DesignMatrices::fit_into`.
+#[allow(clippy::too_many_arguments)]
+fn fit_design<T: Float>(half_order: usize, weight_factors: &[T], spline_tableau: &[T], weighted_matrix: &[T],
+                        weighted_matrix_norm: T, data: &[T], mode: FitMode<T>, coefficients: &mut [T],
+                        band_matrix: &mut [T], stats: &mut [T]) -> Result<FitDiagnostics<T>, FittingError> {
+    let num_knots = weight_factors.len();
+    check_channel_length(data, num_knots)?;
+    check_fit_mode(mode, half_order, num_knots)?;
+
+    let epsilon = smoothing_tolerance();
+    let mut evaluations = 0;
+    let mut evaluate = |smoothing: T| -> Result<(T, T), FittingError> {
+        evaluations += 1;
+        let criterion = fit_spline_coefficients_with_stats(
+            half_order, data, weight_factors, mode, smoothing, epsilon, spline_tableau, weighted_matrix,
+            weighted_matrix_norm, coefficients, stats, band_matrix)?;
+        Ok((criterion, stats[3]))
+    };
+
+    let smoothing = select_smoothing(mode, T::from(1.).expect("Cannot convert to type from f64")
+        / weighted_matrix_norm, &mut evaluate)?;
 
+    // Report the smoothing parameter of the final system, which is bounded close to interpolation or a least-squares
+    // polynomial
+    Ok(FitDiagnostics::from_stats(stats, effective_smoothing(smoothing, epsilon, weighted_matrix_norm), evaluations))
+}
+
+/// Chooses the smoothing parameter according to `mode`, leaving the final fit behind in `evaluate`. Modes that search
+/// for the smoothing parameter start at `initial_smoothing`, where the fitting system is balanced.
+pub(crate) fn select_smoothing<T: Float, F>(mode: FitMode<T>, initial_smoothing: T, mut evaluate: F)
+    -> Result<T, FittingError> where F: FnMut(T) -> Result<(T, T), FittingError> {
+    let smoothing = match mode {
+        FitMode::Interpolate => {
+            evaluate(T::from(0.).expect("Cannot convert to type from f64"))?;
+            T::from(0.).expect("Cannot convert to type from f64")
+        }
+        FitMode::SmoothingParameter(smoothing) => {
+            evaluate(smoothing)?;
+            smoothing
+        }
+        // Zero variance case
+        FitMode::KnownVariance(variance)
+            if variance == T::from(0.).expect("Cannot convert to type from f64") => {
+            evaluate(T::from(0.).expect("Cannot convert to type from f64"))?;
+            T::from(0.).expect("Cannot convert to type from f64")
+        }
+        FitMode::Gcv | FitMode::KnownVariance(_) | FitMode::DegreesOfFreedom(_) => {
+            minimize_criterion(initial_smoothing, &mut evaluate)?
+        }
+    };
+    Ok(smoothing)
+}
+
+/// Relative tolerance used to bound the smoothing parameter.
+pub(crate) fn smoothing_tolerance<T: Float>() -> T {
+    T::from(1E-15).expect("Cannot convert to type from f64")
+}
+
+/// Searches for the smoothing parameter minimizing a fitting criterion, starting at `initial_smoothing`. `evaluate`
+/// fits the spline at a smoothing parameter and returns the criterion value along with the ratio p / (1 + p), which
+/// is clamped to 0 or 1 when the fit degenerates to interpolation or a least-squares polynomial. Returns the smoothing
+/// parameter of the final evaluation, which describes the fit left behind by `evaluate`.
+pub(crate) fn minimize_criterion<T: Float, F>(initial_smoothing: T, mut evaluate: F) -> Result<T, FittingError>
+    where F: FnMut(T) -> Result<(T, T), FittingError> {
     let smoothing_ratio = T::from(2.).expect("Cannot convert to type from f64");
     let tau = T::from(1.618033983).expect("Cannot convert to type from f64");
-    let epsilon = T::from(1E-15).expect("Cannot convert to type from f64");
     let tolerance = T::from(1E-6).expect("Cannot convert to type from f64");
-    let mut coefficients = vec![T::from(0.).expect("Cannot convert to type from f64"); num_knots];
-    let mut stats = vec![T::from(0.).expect("Cannot convert to type from f64"); 6];
-
-    // Compute design matrices and norms
-    let (spline_tableau, basis_l1_norm): (Vec<T>, T) = create_basis(half_order, &knots)?;
-    let (weighted_matrix, mut weighted_matrix_norm): (Vec<T>, T)
-        = create_weighted_matrix(half_order, &knots, &weight_factors)?;
-    weighted_matrix_norm = weighted_matrix_norm / basis_l1_norm;
 
-    // Store temporary GCV function values
+    // Store temporary criterion values
     let (mut gcv_f1, mut gcv_f2, mut gcv_f3, mut gcv_f4): (T, T, T, T);
     let (mut smoothing_1, mut smoothing_2, mut smoothing_3, mut smoothing_4): (T, T, T, T);
-    let mut traced_matrix = vec![T::from(0.).expect("Cannot convert to type from f64");
-                                 weighted_matrix.len()];
-    // Zero variance case
-    if error_variance == T::from(0.).expect("Cannot convert to type from f64") {
-        smoothing_1 = T::from(0.).expect("Cannot convert to type from f64");
-        let _gcv_f1 = fit_spline_coefficients_with_stats(
-            half_order, &data, &weight_factors, error_variance, smoothing_1, epsilon, &spline_tableau,
-            &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-            &mut traced_matrix)?;
-    } else {
-        let mut solved = false;
-        smoothing_1 = T::from(1.).expect("Cannot convert to type from f64") / weighted_matrix_norm;
-        smoothing_2 = smoothing_1 * smoothing_ratio;
-        gcv_f2 = fit_spline_coefficients_with_stats(
-            half_order, &data, &weight_factors, error_variance, smoothing_2, epsilon, &spline_tableau,
-            &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-            &mut traced_matrix)?;
-        gcv_f1 = fit_spline_coefficients_with_stats(
-            half_order, &data, &weight_factors, error_variance, smoothing_1, epsilon, &spline_tableau,
-            &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-            &mut traced_matrix)?;
-        while gcv_f1 <= gcv_f2 && !solved {
-            if stats[3] <= T::from(0.).expect("Cannot convert to type from f64") {
-                solved = true;
-            } else {
-                smoothing_2 = smoothing_1;
-                gcv_f2 = gcv_f1;
-                smoothing_1 = smoothing_1 / smoothing_ratio;
-                gcv_f1 = fit_spline_coefficients_with_stats(
-                    half_order, &data, &weight_factors, error_variance, smoothing_1, epsilon, &spline_tableau,
-                    &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                    &mut traced_matrix)?;
-            }
+    let mut ratio: T;
+
+    smoothing_1 = initial_smoothing;
+    smoothing_2 = smoothing_1 * smoothing_ratio;
+    (gcv_f2, _) = evaluate(smoothing_2)?;
+    (gcv_f1, ratio) = evaluate(smoothing_1)?;
+    while gcv_f1 <= gcv_f2 {
+        if ratio <= T::from(0.).expect("Cannot convert to type from f64") {
+            return Ok(smoothing_1);
         }
-        if !solved {
-            smoothing_3 = smoothing_2 * smoothing_ratio;
-            gcv_f3 = fit_spline_coefficients_with_stats(
-                half_order, &data, &weight_factors, error_variance, smoothing_3, epsilon, &spline_tableau,
-                &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                &mut traced_matrix)?;
-            while gcv_f3 <= gcv_f2 && !solved {
-                if stats[3] >= T::from(1.).expect("Cannot convert to type from f64") {
-                    solved = true;
-                } else {
-                    gcv_f2 = gcv_f3;
-                    smoothing_3 = smoothing_3 * smoothing_ratio;
-                    gcv_f3 = fit_spline_coefficients_with_stats(
-                        half_order, &data, &weight_factors, error_variance, smoothing_3, epsilon, &spline_tableau,
-                        &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                        &mut traced_matrix)?;
-                }
+        smoothing_2 = smoothing_1;
+        gcv_f2 = gcv_f1;
+        smoothing_1 = smoothing_1 / smoothing_ratio;
+        (gcv_f1, ratio) = evaluate(smoothing_1)?;
+    }
+
+    smoothing_3 = smoothing_2 * smoothing_ratio;
+    (gcv_f3, ratio) = evaluate(smoothing_3)?;
+    while gcv_f3 <= gcv_f2 {
+        if ratio >= T::from(1.).expect("Cannot convert to type from f64") {
+            return Ok(smoothing_3);
+        }
+        gcv_f2 = gcv_f3;
+        smoothing_3 = smoothing_3 * smoothing_ratio;
+        (gcv_f3, ratio) = evaluate(smoothing_3)?;
+    }
+
+    // Golden section search between the bracketing smoothing parameters
+    smoothing_2 = smoothing_3;
+    let mut alpha = (smoothing_2 - smoothing_1) / tau;
+    smoothing_4 = smoothing_1 + alpha;
+    smoothing_3 = smoothing_2 - alpha;
+    (gcv_f3, _) = evaluate(smoothing_3)?;
+    (gcv_f4, _) = evaluate(smoothing_4)?;
+    loop {
+        if gcv_f3 <= gcv_f4 {
+            smoothing_2 = smoothing_4;
+            let error = (smoothing_2 - smoothing_1) / (smoothing_1 + smoothing_2);
+            if error * error + T::from(1.).expect("Cannot convert to type from f64")
+                == T::from(1.).expect("Cannot convert to type from f64") || error <= tolerance {
+                break;
             }
-            if !solved {
-                smoothing_2 = smoothing_3;
-                let mut alpha = (smoothing_2 - smoothing_1) / tau;
-                smoothing_4 = smoothing_1 + alpha;
-                smoothing_3 = smoothing_2 - alpha;
-                gcv_f3 = fit_spline_coefficients_with_stats(
-                    half_order, &data, &weight_factors, error_variance, smoothing_3, epsilon, &spline_tableau,
-                    &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                    &mut traced_matrix)?;
-                gcv_f4 = fit_spline_coefficients_with_stats(
-                    half_order, &data, &weight_factors, error_variance, smoothing_4, epsilon, &spline_tableau,
-                    &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                    &mut traced_matrix)?;
-                while !solved {
-                    if gcv_f3 <= gcv_f4 {
-                        smoothing_2 = smoothing_4;
-                        let error = (smoothing_2 - smoothing_1) / (smoothing_1 + smoothing_2);
-                        if error * error + T::from(1.).expect("Cannot convert to type from f64")
-                            == T::from(1.).expect("Cannot convert to type from f64") || error <= tolerance {
-                            solved = true;
-                        } else {
-                            smoothing_4 = smoothing_3;
-                            gcv_f4 = gcv_f3;
-                            alpha = alpha / tau;
-                            smoothing_3 = smoothing_2 - alpha;
-                            gcv_f3 = fit_spline_coefficients_with_stats(
-                                half_order, &data, &weight_factors, error_variance, smoothing_3, epsilon, &spline_tableau,
-                                &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                                &mut traced_matrix)?;
-                        }
-                    } else {
-                        smoothing_1 = smoothing_3;
-                        let error = (smoothing_2 - smoothing_1) / (smoothing_1 + smoothing_2);
-                        if error * error + T::from(1.).expect("Cannot convert to type from f64")
-                            <= T::from(1.).expect("Cannot convert to type from f64") || error <= tolerance {
-                            solved = true;
-                        } else {
-                            smoothing_3 = smoothing_4;
-                            gcv_f3 = gcv_f4;
-                            alpha = alpha / tau;
-                            smoothing_4 = smoothing_1 + alpha;
-                            gcv_f4 = fit_spline_coefficients_with_stats(half_order, &data, &weight_factors,
-                                error_variance, smoothing_4, epsilon, &spline_tableau,
-                                &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                                &mut traced_matrix)?;
-                        }
-                    }
-                }
-                smoothing_1 = T::from(0.5).expect("Cannot convert to type from f64") *
-                    (smoothing_1 + smoothing_2);
-                let _gcv_f1 = fit_spline_coefficients_with_stats(
-                    half_order, &data, &weight_factors, error_variance, smoothing_1, epsilon, &spline_tableau,
-                    &weighted_matrix, weighted_matrix_norm, &mut coefficients, &mut stats,
-                    &mut traced_matrix)?;
+            smoothing_4 = smoothing_3;
+            gcv_f4 = gcv_f3;
+            alpha = alpha / tau;
+            smoothing_3 = smoothing_2 - alpha;
+            (gcv_f3, _) = evaluate(smoothing_3)?;
+        } else {
+            smoothing_1 = smoothing_3;
+            let error = (smoothing_2 - smoothing_1) / (smoothing_1 + smoothing_2);
+            if error * error + T::from(1.).expect("Cannot convert to type from f64")
+                <= T::from(1.).expect("Cannot convert to type from f64") || error <= tolerance {
+                break;
             }
+            smoothing_3 = smoothing_4;
+            gcv_f3 = gcv_f4;
+            alpha = alpha / tau;
+            smoothing_4 = smoothing_1 + alpha;
+            (gcv_f4, _) = evaluate(smoothing_4)?;
         }
     }
+    smoothing_1 = T::from(0.5).expect("Cannot convert to type from f64") * (smoothing_1 + smoothing_2);
+    evaluate(smoothing_1)?;
 
-    Ok(coefficients)
-}
\ No newline at end of file
+    Ok(smoothing_1)
+}
@@ -1,10 +1,10 @@
-mod basis;
+pub(crate) mod basis;
 pub mod support;
-mod prep;
-mod bandet;
-mod bansol;
-mod trinv;
-mod splc;
-mod search;
+pub(crate) mod prep;
+pub(crate) mod bandet;
+pub(crate) mod bansol;
+pub(crate) mod trinv;
+pub(crate) mod splc;
+pub(crate) mod search;
 pub(crate) mod gcvspl;
 pub(crate) mod splder;
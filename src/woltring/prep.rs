@@ -1,15 +1,25 @@
+use alloc::{vec, vec::Vec};
 use num_traits::Float;
-use crate::woltring::support::{check_increasing, check_order, check_vector_length, FittingError};
+use crate::woltring::support::{check_increasing, check_order, check_vector_length, check_workspace, FittingError};
 
-pub(crate) fn create_weighted_matrix<T: Float>(half_order: usize, knots: &Vec<T>, weights_diagonal: &Vec<T>)
+pub(crate) fn create_weighted_matrix<T: Float>(half_order: usize, knots: &[T], weights_diagonal: &[T])
     -> Result<(Vec<T>, T), FittingError> {
+    let mut weighted_matrix = vec![T::from(0.).expect("Cannot convert to type from f64");
+                                   ((2 * half_order) + 1) * knots.len()];
+    let matrix_norm = create_weighted_matrix_into(half_order, knots, weights_diagonal, &mut weighted_matrix)?;
+    Ok((weighted_matrix, matrix_norm))
+}
+
+/// Computes the weighted penalty matrix like `create_weighted_matrix`, writing it into `weighted_matrix` of length
+/// `knots.len()` * (2 * `half_order` + 1). Returns the L1 norm of the matrix.
+pub(crate) fn create_weighted_matrix_into<T: Float>(half_order: usize, knots: &[T], weights_diagonal: &[T],
+                                                    weighted_matrix: &mut [T]) -> Result<T, FittingError> {
     let num_knots = knots.len();
-    check_increasing(knots)?;
     check_order(half_order, num_knots)?;
+    check_increasing(knots)?;
     check_vector_length(weights_diagonal, num_knots)?;
-
-    let mut weighted_matrix = vec![T::from(0.).expect("Cannot convert to type from f64");
-                                   ((2 * half_order) + 1) * num_knots];
+    check_workspace(weighted_matrix, ((2 * half_order) + 1) * num_knots)?;
+    weighted_matrix.fill(T::from(0.).expect("Cannot convert to type from f64"));
 
     // Calculate factor
     let mut factor_1 = T::from(-1.).expect("Cannot convert to type from f64");
@@ -55,7 +65,7 @@ pub(crate) fn create_weighted_matrix<T: Float>(half_order: usize, knots: &Vec<T>
         }
         weighted_matrix[matrix_index - 1] = matrix_factor;
         matrix_index += half_order * 2;
-        if index_1 + 1 <= index_2 - 1 {
+        if index_1 < index_2 - 1 {
             for outer in index_1 + 1 ..= index_2 - 1 {
                 matrix_factor = factor;
                 knot_value = knots[outer - 1];
@@ -91,5 +101,5 @@ pub(crate) fn create_weighted_matrix<T: Float>(half_order: usize, knots: &Vec<T>
     }
     matrix_norm = matrix_norm / T::from(num_knots).expect("Cannot convert to type from usize");
 
-    Ok((weighted_matrix, matrix_norm))
+    Ok(matrix_norm)
 }
\ No newline at end of file
@@ -1,7 +1,31 @@
 use num_traits::Float;
 
-pub(crate) fn find_knot_interval<T: Float>(knots: &Vec<T>, point: T, knot_guess: usize) -> usize {
-    if point < knots[0] {
+/// Guesses the knot interval containing a point, assuming uniformly spaced knots. The guess only serves as a starting
+/// point for `find_knot_interval`.
+pub(crate) fn guess_knot_interval<T: Float>(knots: &[T], point: T) -> usize {
+    let start = knots[0];
+    let end = knots[knots.len() - 1];
+    ((point - start) / (end - start) * T::from(knots.len()).expect("Cannot convert to type from usize"))
+        .to_usize().unwrap_or(0)
+}
+
+/// Finds the knot interval containing a point by walking from the knot interval `start`, taking time proportional to
+/// the number of knots passed. Intervals are numbered as in `find_knot_interval`.
+pub(crate) fn walk_knot_interval<T: Float>(knots: &[T], point: T, start: usize) -> usize {
+    let num_knots = knots.len();
+    let mut knot_interval = start.min(num_knots);
+    while knot_interval < num_knots && point >= knots[knot_interval] {
+        knot_interval += 1;
+    }
+    while knot_interval > 0 && point < knots[knot_interval - 1] {
+        knot_interval -= 1;
+    }
+    knot_interval
+}
+
+pub(crate) fn find_knot_interval<T: Float>(knots: &[T], point: T, knot_guess: usize) -> usize {
+    // NaN compares false with every knot, so it would walk below the first interval
+    if point.is_nan() || point < knots[0] {
         return 0;
     }
     let num_knots = knots.len();
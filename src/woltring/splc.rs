@@ -1,45 +1,142 @@
 use num_traits::Float;
-use crate::woltring::bandet::{consume_and_decompose};
+use crate::woltring::bandet::decompose_in_place;
 use crate::woltring::bansol::solve_decomposed_system;
-use crate::woltring::support::{check_order, check_vector_length, FittingError};
+use crate::woltring::support::{check_channel_length, check_matrix_size, check_order, check_vector_length, FitMode,
+                               FittingError};
 use crate::woltring::trinv::trace_inverse;
 
-pub(crate) fn fit_spline_coefficients_with_stats<T: Float>(half_order: usize, data: &Vec<T>,
-                                          weight_factors: &Vec<T>, variance: T,
+/// Fits the spline coefficients of all data channels at a smoothing parameter and returns the value of the fitting
+/// criterion selected by `mode`. `traced_current` is used as working storage for the banded system and holds the
+/// central band of its inverse on return.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn fit_spline_coefficients_with_stats<T: Float>(half_order: usize, data: &[T],
+                                          weight_factors: &[T], mode: FitMode<T>,
                                           real_smoothing: T, tolerance: T,
-                                          spline_tableau: &Vec<T>, weighted_tableau: &Vec<T>,
-                                          weighted_norm: T, coefs_current: &mut Vec<T>,
-                                          stats_current: &mut Vec<T>,
-                                          traced_current: &mut Vec<T> )
+                                          spline_tableau: &[T], weighted_tableau: &[T],
+                                          weighted_norm: T, coefs_current: &mut [T],
+                                          stats_current: &mut [T],
+                                          traced_current: &mut [T] )
                                           -> Result<T, FittingError> {
     let num_knots: usize = spline_tableau.len() / (2 * half_order - 1);
     check_order(half_order, num_knots)?;
-    check_vector_length(data, num_knots)?;
+    check_channel_length(data, num_knots)?;
     check_vector_length(weight_factors, num_knots)?;
+    check_matrix_size(data, coefs_current)?;
+    check_matrix_size(weighted_tableau, traced_current)?;
+    let num_channels = data.len() / num_knots;
 
-    let mut smoothing = real_smoothing;
-    let mut stats = vec![T::from(0.).expect("Cannot convert to type from f64"); 6];
-    let mut inverted_weighted_matrix = vec![T::from(0.).expect("Cannot convert to type from f64");
-                                            weighted_tableau.len()];
+    let stats = stats_current;
+    let inverted_weighted_matrix = traced_current;
+    stats[3] = assemble_system(half_order, real_smoothing, tolerance, spline_tableau, weighted_tableau,
+                               weighted_norm, inverted_weighted_matrix);
+    let smoothing = effective_smoothing(real_smoothing, tolerance, weighted_norm);
+
+    // Solve matrix system inverted_weighted_matrix * coefficients = data,
+    // evaluate TRACE[spline_tableau * inverted_weighted_matrix**-1]
+    decompose_in_place(inverted_weighted_matrix, half_order)?;
+    for (channel_data, channel_coefficients) in data.chunks(num_knots).zip(coefs_current.chunks_mut(num_knots)) {
+        solve_decomposed_system(inverted_weighted_matrix, channel_data, half_order, channel_coefficients)?;
+    }
+    let coefficients = &*coefs_current;
+    let trace = trace_inverse(weighted_tableau, inverted_weighted_matrix, half_order)?;
+    let degrees_of_freedom = smoothing * trace; // TRACE[I - influence matrix]
+
+    // Compute mean squared weighted residual over all channels
+    let mut residual = T::from(0.).expect("Cannot convert to type from f64");
+    for (channel_data, channel_coefficients) in data.chunks(num_knots).zip(coefficients.chunks(num_knots)) {
+        for knot_index in 1 ..= num_knots {
+            let mut point = -channel_data[knot_index - 1];
+
+            let lower_bound = -((half_order - 1).min(knot_index - 1) as i32);
+            let upper_bound = (half_order - 1).min(num_knots - knot_index) as i32;
+
+            for inner in lower_bound ..= upper_bound {
+                let index = ((knot_index as i32 - 1) *
+                    (half_order as i32 * 2 - 1) + inner + half_order as i32 - 1) as usize;
+                point = point + (spline_tableau[index]
+                    * channel_coefficients[(knot_index as i32 + inner - 1) as usize]);
+            }
+            residual = residual + (point * point * weight_factors[knot_index - 1]);
+        }
+    }
+    residual = residual / T::from(num_knots * num_channels).expect("Cannot convert to type from usize");
+
+    Ok(criterion_from_residual(mode, residual, degrees_of_freedom, num_knots, stats))
+}
+
+/// Computes the statistics of a fit from its mean squared weighted residual and the degrees of freedom of the
+/// residual, TRACE[I - influence matrix], and returns the value of the fitting criterion selected by `mode`. Fills all
+/// of `stats` except the ratio p / (1 + p) in `stats[3]`.
+pub(crate) fn criterion_from_residual<T: Float>(mode: FitMode<T>, residual: T, degrees_of_freedom: T,
+                                                num_knots: usize, stats: &mut [T]) -> T {
     let splc: T;
+    stats[2] = degrees_of_freedom;
+    let normalized_trace = degrees_of_freedom / T::from(num_knots).expect("Cannot convert to type from usize");
 
-    stats[3] = real_smoothing / (T::from(1.).expect("Cannot convert to type from f64") + real_smoothing);
+    let estimated_variance = residual / normalized_trace; // Estimated variance
+    stats[5] = estimated_variance;
+    stats[0] = estimated_variance / normalized_trace; // GCV function value
+    stats[1] = residual; // mean squared residual
+
+    match mode {
+        FitMode::KnownVariance(variance) => {
+            // Known variance: estimated mean squared error
+            stats[4] = residual - variance * (T::from(2.).expect("Cannot convert to type from f64")
+                * normalized_trace - T::from(1.).expect("Cannot convert to type from f64"));
+            splc = stats[4];
+        }
+        FitMode::DegreesOfFreedom(dof) => {
+            // Prescribed degrees of freedom: squared distance from the requested trace
+            stats[4] = estimated_variance - residual;
+            splc = (stats[2] - dof) * (stats[2] - dof);
+        }
+        FitMode::Interpolate | FitMode::Gcv | FitMode::SmoothingParameter(_) => {
+            // Unknown variance: GCV
+            stats[4] = estimated_variance - residual;
+            splc = stats[0];
+        }
+    }
+    splc
+}
 
-    // Pseudo least squares polynomial if smoothing parameter is too large
+/// Returns the smoothing parameter actually used for a requested smoothing parameter, which is bounded to avoid
+/// ill-conditioned systems close to interpolation or a least-squares polynomial.
+pub(crate) fn effective_smoothing<T: Float>(real_smoothing: T, tolerance: T, weighted_norm: T) -> T {
     if real_smoothing * weighted_norm * tolerance > T::from(1.).expect("Cannot convert to type from f64") {
-        stats[3] = T::from(1.).expect("Cannot convert to type from f64");
-        smoothing = T::from(1.).expect("Cannot convert to type from f64") / (tolerance * weighted_norm);
+        // Pseudo least squares polynomial if smoothing parameter is too large
+        T::from(1.).expect("Cannot convert to type from f64") / (tolerance * weighted_norm)
+    } else if real_smoothing * weighted_norm < tolerance {
+        // Pseudo interpolation if smoothing parameter is too small
+        tolerance / weighted_norm
+    } else {
+        real_smoothing
     }
+}
 
-    // Pseudo interpolation if smoothing parameter is too small
-    if real_smoothing * weighted_norm < tolerance {
-        smoothing = tolerance / weighted_norm;
-        stats[3] = T::from(0.).expect("Cannot convert to type from f64");
+/// Returns the ratio p / (1 + p) of a requested smoothing parameter, clamped to 0 or 1 if the smoothing parameter has
+/// to be bounded.
+pub(crate) fn smoothing_ratio<T: Float>(real_smoothing: T, tolerance: T, weighted_norm: T) -> T {
+    if real_smoothing * weighted_norm * tolerance > T::from(1.).expect("Cannot convert to type from f64") {
+        T::from(1.).expect("Cannot convert to type from f64")
+    } else if real_smoothing * weighted_norm < tolerance {
+        T::from(0.).expect("Cannot convert to type from f64")
+    } else {
+        real_smoothing / (T::from(1.).expect("Cannot convert to type from f64") + real_smoothing)
     }
+}
+
+/// Writes the banded matrix spline_tableau + p * weighted_tableau of the fitting system into `matrix` and returns the
+/// ratio p / (1 + p), clamped to 0 or 1 if the smoothing parameter had to be bounded.
+pub(crate) fn assemble_system<T: Float>(half_order: usize, real_smoothing: T, tolerance: T, spline_tableau: &[T],
+                                        weighted_tableau: &[T], weighted_norm: T, matrix: &mut [T]) -> T {
+    let num_knots: usize = spline_tableau.len() / (2 * half_order - 1);
+    let smoothing = effective_smoothing(real_smoothing, tolerance, weighted_norm);
+    let ratio = smoothing_ratio(real_smoothing, tolerance, weighted_norm);
+    matrix.fill(T::from(0.).expect("Cannot convert to type from f64"));
 
     // Calculate inverted weighted matrix
     for knot_index in 1 ..= num_knots {
-        let lower_bound = -1 * half_order.min(knot_index - 1) as i32;
+        let lower_bound = -(half_order.min(knot_index - 1) as i32);
         let upper_bound = half_order.min(num_knots - knot_index) as i32;
 
         for inner in lower_bound ..= upper_bound {
@@ -47,58 +144,36 @@ pub(crate) fn fit_spline_coefficients_with_stats<T: Float>(half_order: usize, da
                 half_order as i32) as usize;
             let index_b = ((knot_index as i32 - 1) * (half_order as i32 * 2 - 1) + inner +
                 half_order as i32 - 1) as usize;
-            if inner.abs() as usize == half_order {
-                inverted_weighted_matrix[index] = smoothing * weighted_tableau[index];
+            if inner.unsigned_abs() as usize == half_order {
+                matrix[index] = smoothing * weighted_tableau[index];
             } else {
-                inverted_weighted_matrix[index] = spline_tableau[index_b] + smoothing * weighted_tableau[index];
+                matrix[index] = spline_tableau[index_b] + smoothing * weighted_tableau[index];
             }
         }
     }
+    ratio
+}
 
-    // Solve matrix system inverted_weighted_matrix * coefficients = data,
-    // evaluate TRACE[spline_tableau * inverted_weighted_matrix**-1]
-    let decomposed_weighted_matrix = consume_and_decompose(inverted_weighted_matrix, half_order)?;
-    let coefficients = solve_decomposed_system(&decomposed_weighted_matrix, data, half_order)?;
-    let (traced_matrix, trace): (Vec<T>, T) = trace_inverse(weighted_tableau, decomposed_weighted_matrix, half_order)?;
-    stats[2] = trace;
-    let normalized_trace = trace / T::from(num_knots).expect("Cannot convert to type from usize");
+/// Computes the diagonal of the influence matrix, spline_tableau * inverse(fitting system), from the central band of
+/// the inverse of the fitting system left in `traced_matrix` by `trace_inverse`.
+pub(crate) fn influence_diagonal<T: Float>(half_order: usize, spline_tableau: &[T], traced_matrix: &[T],
+                                           leverage: &mut [T]) -> Result<(), FittingError> {
+    let num_knots: usize = spline_tableau.len() / (2 * half_order - 1);
+    check_vector_length(leverage, num_knots)?;
 
-    // Compute mean squared weighted residual
-    let mut residual = T::from(0.).expect("Cannot convert to type from f64");
     for knot_index in 1 ..= num_knots {
-        let mut point = -data[knot_index - 1];
-
-        let lower_bound = -1 * (half_order - 1).min(knot_index - 1) as i32;
+        let lower_bound = -((half_order - 1).min(knot_index - 1) as i32);
         let upper_bound = (half_order - 1).min(num_knots - knot_index) as i32;
 
+        let mut diagonal = T::from(0.).expect("Cannot convert to type from f64");
         for inner in lower_bound ..= upper_bound {
-            let index = ((knot_index as i32 - 1) *
-                (half_order as i32 * 2 - 1) + inner + half_order as i32 - 1) as usize;
-            point = point + (spline_tableau[index]
-                * coefficients[(knot_index as i32 + inner - 1) as usize]);
+            let index_b = ((knot_index as i32 - 1) * (half_order as i32 * 2 - 1) + inner +
+                half_order as i32 - 1) as usize;
+            let index = ((knot_index as i32 + inner - 1) * (half_order as i32 * 2 + 1) - inner +
+                half_order as i32) as usize;
+            diagonal = diagonal + spline_tableau[index_b] * traced_matrix[index];
         }
-        residual = residual + (point * point * weight_factors[knot_index - 1]);
+        leverage[knot_index - 1] = diagonal;
     }
-    residual = residual / T::from(num_knots).expect("Cannot convert to type from usize");
-
-    let estimated_variance = residual / normalized_trace; // Estimated variance
-    stats[5] = estimated_variance;
-    stats[0] = estimated_variance / normalized_trace; // GCV function value
-    stats[1] = residual; // mean squared residual
-
-    if variance < T::from(0.).expect("Cannot convert to type from f64") {
-        // Unknown variance: GCV
-        stats[4] = estimated_variance - residual;
-        splc = stats[0];
-    } else {
-        // Known variance: estimated mean squared error
-        stats[4] = residual - variance * (T::from(2.).expect("Cannot convert to type from f64")
-            * normalized_trace - T::from(1.).expect("Cannot convert to type from f64"));
-        splc = stats[4];
-    }
-
-    *coefs_current = coefficients;
-    *stats_current = stats;
-    *traced_current = traced_matrix;
-    Ok(splc)
-}
\ No newline at end of file
+    Ok(())
+}
@@ -1,10 +1,17 @@
+use alloc::vec;
 use num_traits::Float;
 use crate::woltring::search::find_knot_interval;
 
-pub(crate) fn evaluate_spline<T: Float>(derivative_order: usize, half_order: usize, point: T, knots: &Vec<T>,
-                       coefficients: &Vec<T>, knot_guess: usize) -> T {
-    let num_knots = knots.len();
+pub(crate) fn evaluate_spline<T: Float>(derivative_order: usize, half_order: usize, point: T, knots: &[T],
+                       coefficients: &[T], knot_guess: usize) -> T {
+    let mut tableau = vec![T::from(0.).expect("Cannot convert to type from f64"); 2 * half_order];
+    evaluate_spline_into(derivative_order, half_order, point, knots, coefficients, knot_guess, &mut tableau)
+}
 
+/// Evaluates the spline like `evaluate_spline`, using `tableau` of length at least 2 * `half_order` as working
+/// storage instead of allocating.
+pub(crate) fn evaluate_spline_into<T: Float>(derivative_order: usize, half_order: usize, point: T, knots: &[T],
+                                             coefficients: &[T], knot_guess: usize, tableau: &mut [T]) -> T {
     // Derivatives of order >= 2 * half_order are always zero
     let order = half_order as i32 * 2 - derivative_order as i32;
     if order < 1 {
@@ -12,16 +19,58 @@ pub(crate) fn evaluate_spline<T: Float>(derivative_order: usize, half_order: usi
     }
 
     // Search for interval value
-    let knot_interval = find_knot_interval(&knots, point, knot_guess);
+    let knot_interval = find_knot_interval(knots, point, knot_guess);
+
+    initialize_tableau(half_order, knots.len(), coefficients, knot_interval, tableau);
+
+    // The following loop computes differences of the B-spline coefficients. If the value of the
+    // spline is required, differencing is not necessary.
+    if derivative_order > 0 {
+        for der_index in 1 ..= derivative_order {
+            difference_tableau(der_index, half_order, knots, knot_interval, tableau);
+        }
+        for idx in 1 ..= order {
+            tableau[idx as usize - 1] = tableau[idx as usize + derivative_order - 1];
+        }
+    }
+
+    evaluate_tableau(derivative_order, half_order, point, knots, knot_interval, tableau)
+}
+
+/// Evaluates the spline and all its derivatives up to `values.len() - 1` at a point, with a single search for the
+/// knot interval. The differences of the B-spline coefficients for each derivative are computed from those of the
+/// previous one.
+pub(crate) fn evaluate_spline_derivatives<T: Float>(half_order: usize, point: T, knots: &[T],
+                                                    coefficients: &[T], knot_guess: usize, values: &mut [T]) {
+    let knot_interval = find_knot_interval(knots, point, knot_guess);
 
-    // Initialize parameters and first row of B-spline coefficients tableau
     let mut tableau = vec![T::from(0.).expect("Cannot convert to type from f64"); 2 * half_order];
-    let mut lower_index = knot_interval as i32 + 1;
+    let mut working = tableau.clone();
+    initialize_tableau(half_order, knots.len(), coefficients, knot_interval, &mut tableau);
+
+    for (derivative_order, value) in values.iter_mut().enumerate() {
+        // Derivatives of order >= 2 * half_order are always zero
+        if derivative_order >= 2 * half_order {
+            *value = T::from(0.).expect("Cannot convert to type from f64");
+            continue;
+        }
+        if derivative_order > 0 {
+            difference_tableau(derivative_order, half_order, knots, knot_interval, &mut tableau);
+        }
+        let order = 2 * half_order - derivative_order;
+        working[.. order].copy_from_slice(&tableau[derivative_order .. derivative_order + order]);
+        *value = evaluate_tableau(derivative_order, half_order, point, knots, knot_interval, &mut working);
+    }
+}
+
+/// Initializes the first row of the B-spline coefficients tableau.
+fn initialize_tableau<T: Float>(half_order: usize, num_knots: usize, coefficients: &[T], knot_interval: usize,
+                                tableau: &mut [T]) {
+    let lower_index = knot_interval as i32 + 1;
     let upper_index = knot_interval as i32 + half_order as i32 * 2;
-    let mut inner_index = num_knots - 2 * half_order;
 
     for index in lower_index ..= upper_index {
-        if index >= half_order as i32 + 1 && index <= num_knots as i32 + half_order as i32 {
+        if index > half_order as i32 && index <= num_knots as i32 + half_order as i32 {
             tableau[(index - knot_interval as i32 - 1) as usize] =
                 coefficients[(index - half_order as i32 - 1) as usize];
         } else {
@@ -29,53 +78,57 @@ pub(crate) fn evaluate_spline<T: Float>(derivative_order: usize, half_order: usi
                 T::from(0.).expect("Cannot convert to type from f64");
         }
     }
+}
 
-    // The following loop computes differences of the B-spline coefficients. If the value of the
-    // spline is required, differencing is not necessary.
-    if derivative_order > 0 {
-        lower_index -= half_order as i32 * 2;
-        let index_bound = half_order as i32 * 2 - knot_interval as i32;
-        for der_index in 1 ..= derivative_order {
-            lower_index += 1;
-            inner_index += 1;
-            let idx_1 = 1.max(lower_index) as usize;
-            let idx_2 = knot_interval.min(inner_index);
-            let mut idx = idx_2 + 1;
-            if idx_1 <= idx_2 {
-                for _ in idx_1 ..= idx_2 {
-                    idx -= 1;
-                    let work_idx = (index_bound + idx as i32) as usize;
-                    tableau[work_idx - 1] = (tableau[work_idx - 1] - tableau[work_idx - 2])
-                        / (knots[idx + half_order * 2 - der_index - 1] - knots[idx - 1]);
-                }
-            }
-            if lower_index < 1 {
-                idx = (index_bound + 1) as usize;
-                if der_index as i32 + 1 <= index_bound {
-                    for _ in der_index as i32 + 1 ..= index_bound {
-                        idx -= 1;
-                        tableau[idx - 1] = -T::from(1.).expect("Cannot convert to type from f64")
-                            * tableau[idx - 2];
-                    }
-                }
-            }
+/// Computes the differences of the B-spline coefficients for derivative `der_index` from those for derivative
+/// `der_index - 1`. The differences are stored from index `der_index` of the tableau onwards.
+fn difference_tableau<T: Float>(der_index: usize, half_order: usize, knots: &[T], knot_interval: usize,
+                                tableau: &mut [T]) {
+    let lower_index = knot_interval as i32 + 1 - half_order as i32 * 2 + der_index as i32;
+    let inner_index = knots.len() - 2 * half_order + der_index;
+    let index_bound = half_order as i32 * 2 - knot_interval as i32;
+
+    let idx_1 = 1.max(lower_index) as usize;
+    let idx_2 = knot_interval.min(inner_index);
+    let mut idx = idx_2 + 1;
+    if idx_1 <= idx_2 {
+        for _ in idx_1 ..= idx_2 {
+            idx -= 1;
+            let work_idx = (index_bound + idx as i32) as usize;
+            tableau[work_idx - 1] = (tableau[work_idx - 1] - tableau[work_idx - 2])
+                / (knots[idx + half_order * 2 - der_index - 1] - knots[idx - 1]);
         }
-        for idx in 1 ..= order {
-            tableau[idx as usize - 1] = tableau[idx as usize + derivative_order - 1];
+    }
+    if lower_index < 1 {
+        idx = (index_bound + 1) as usize;
+        if (der_index as i32) < index_bound {
+            for _ in der_index as i32 + 1 ..= index_bound {
+                idx -= 1;
+                tableau[idx - 1] = -T::from(1.).expect("Cannot convert to type from f64")
+                    * tableau[idx - 2];
+            }
         }
     }
+}
+
+/// Evaluates a derivative of the spline from a tableau holding the differences of the B-spline coefficients for that
+/// derivative at its start.
+fn evaluate_tableau<T: Float>(derivative_order: usize, half_order: usize, point: T, knots: &[T],
+                              knot_interval: usize, tableau: &mut [T]) -> T {
+    let num_knots = knots.len();
+    let order = half_order as i32 * 2 - derivative_order as i32;
 
     let mut solution;
 
     // Compute lower half of the evaluation tableau
-    if order - 1 >= 1 { // Tableau is ready if derivative order == 2 * half_order - 1
+    if order > 1 { // Tableau is ready if derivative order == 2 * half_order - 1
         for idx in 1 ..= order as usize - 1 {
             let order_idx = (num_knots as i32 - order) as usize + idx;
             let mut working_idx = order as usize;
             let mut knot_idx = knot_interval;
 
             // Right hand splines
-            if knot_interval >= order_idx + 1 {
+            if knot_interval > order_idx {
                 for _ in order_idx + 1 ..= knot_interval {
                     tableau[working_idx - 1] = tableau[working_idx - 2] + (point - knots[knot_idx - 1])
                         * tableau[working_idx - 1];
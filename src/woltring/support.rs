@@ -1,17 +1,95 @@
-use std::{error::Error, fmt};
+use alloc::{format, string::{String, ToString}};
+use core::fmt;
 use num_traits::Float;
 
-/// Reports input errors that prevent fitting a GCV spline.
+/// Reports input errors that prevent fitting a GCV spline. New kinds of errors may be added, so matches on this enum
+/// need a wildcard arm.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum FittingError {
     NotEnoughKnotsForOrder(String),
     VectorLengthMismatch(String),
     KnotsNotStrictlyIncreasing(String),
     InsufficientKnots(String),
     MatrixMismatch(String),
+    InvalidFitMode(String),
+    GapTooLong(String),
+    MissingFitData(String),
+    InvalidLevel(String),
+    PointOutOfRange(String),
+    InvalidResampling(String),
+    InvalidPeriod(String),
+    InvalidQuaternion(String),
+    InsufficientWorkspace(String),
+    InsufficientSamples(String),
+    SingularSystem(String),
+    InvalidFullTurn(String),
+    NonFiniteCoefficients(String),
+    InvalidHalfOrder(String),
+    NonFiniteKnots(String),
+    InvalidDiagnostics(String),
 }
 
-impl Error for FittingError {}
+/// Selects how the smoothing parameter of a GCV spline is chosen. These correspond to the modes of Woltring's GCVSPL,
+/// with exact interpolation given its own variant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FitMode<T: Float> {
+    /// Interpolates the data without smoothing.
+    Interpolate,
+    /// Chooses the smoothing parameter minimizing the generalized cross-validation criterion.
+    Gcv,
+    /// Chooses the smoothing parameter minimizing the estimated mean squared error, given the known variance of the
+    /// measurement errors. A variance of 0 interpolates the data.
+    KnownVariance(T),
+    /// Uses the provided smoothing parameter directly. A smoothing parameter of 0 interpolates the data.
+    SmoothingParameter(T),
+    /// Chooses the smoothing parameter giving the provided number of degrees of freedom of the residual sum of
+    /// squares. This must lie between 0 (interpolation) and the number of knots minus the half-order (least-squares
+    /// polynomial).
+    DegreesOfFreedom(T),
+}
+
+/// Statistics describing a fitted GCV spline, as reported by Woltring's GCVSPL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FitDiagnostics<T: Float> {
+    /// Value of the generalized cross-validation function.
+    pub gcv: T,
+    /// Mean squared weighted residual.
+    pub mean_squared_residual: T,
+    /// Estimated degrees of freedom of the residual sum of squares, TRACE[I - influence matrix].
+    pub degrees_of_freedom: T,
+    /// Ratio p / (1 + p) of the smoothing parameter p, set to 0 for interpolation and 1 for a least-squares
+    /// polynomial.
+    pub smoothing_ratio: T,
+    /// Estimated mean squared error of the fit. This uses the known variance when fitting with
+    /// [`FitMode::KnownVariance`] and the estimated variance otherwise.
+    pub mean_squared_error: T,
+    /// Estimated variance of the measurement errors.
+    pub estimated_variance: T,
+    /// Smoothing parameter of the final fit. Requested smoothing parameters are bounded to avoid ill-conditioned
+    /// systems close to interpolation or a least-squares polynomial, so this is the bounded value.
+    pub smoothing_parameter: T,
+    /// Number of times the fitting criterion was evaluated while choosing the smoothing parameter.
+    pub evaluations: usize,
+}
+
+impl<T: Float> FitDiagnostics<T> {
+    pub(crate) fn from_stats(stats: &[T], smoothing_parameter: T, evaluations: usize) -> Self {
+        FitDiagnostics {
+            gcv: stats[0],
+            mean_squared_residual: stats[1],
+            degrees_of_freedom: stats[2],
+            smoothing_ratio: stats[3],
+            mean_squared_error: stats[4],
+            estimated_variance: stats[5],
+            smoothing_parameter,
+            evaluations,
+        }
+    }
+}
+
+impl core::error::Error for FittingError {}
 impl fmt::Display for FittingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "FittingError: {:?}", self)
@@ -19,37 +97,57 @@ impl fmt::Display for FittingError {
 }
 
 pub(crate) fn check_order(half_order: usize, num_knots: usize) -> Result<(), FittingError> {
+    if half_order == 0 {
+        return Err(FittingError::InvalidHalfOrder(String::from("Half-order must be at least 1")));
+    }
     if num_knots < 2 * half_order {
         return Err(FittingError::NotEnoughKnotsForOrder(
-            String::from(format!(
-                "At least {} knots (time points) needed, {} provided", 2 * half_order, num_knots
-            )
-            )
+            format!("At least {} knots (time points) needed, {} provided", 2 * half_order, num_knots)
         ));
     }
     Ok(())
 }
 
-pub(crate) fn check_vector_length<T: Float>(vector: &Vec<T>, num_knots: usize) -> Result<(), FittingError> {
+pub(crate) fn check_vector_length<T: Float>(vector: &[T], num_knots: usize) -> Result<(), FittingError> {
     if vector.len() != num_knots {
         return Err(FittingError::VectorLengthMismatch(
-            String::from(format!(
-                "Vector length {} must match number of knots (time points), {}",
-                vector.len(), num_knots
-            ))
+            format!("Vector length {} must match number of knots (time points), {}", vector.len(), num_knots)
         ));
     }
     Ok(())
 }
 
-pub(crate) fn check_increasing<T: Float>(knots: &Vec<T>) -> Result<(), FittingError> {
+pub(crate) fn check_channel_length<T: Float>(vector: &[T], num_knots: usize) -> Result<(), FittingError> {
+    if vector.is_empty() || vector.len() % num_knots != 0 {
+        return Err(FittingError::VectorLengthMismatch(format!(
+            "Vector length {} must be a non-zero multiple of the number of knots (time points), {}",
+            vector.len(), num_knots
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_workspace<T: Float>(workspace: &[T], required: usize) -> Result<(), FittingError> {
+    if workspace.len() < required {
+        return Err(FittingError::InsufficientWorkspace(format!(
+            "Workspace length {} is less than the required length, {}", workspace.len(), required
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_increasing<T: Float>(knots: &[T]) -> Result<(), FittingError> {
     if knots.len() < 2 {
         return Err(FittingError::InsufficientKnots(String::from("At least 2 knots (time points) are needed")));
     }
 
+    if !knots.iter().all(|knot| knot.is_finite()) {
+        return Err(FittingError::NonFiniteKnots(String::from("Knots (time points) must be finite")));
+    }
+
     let mut knots_iter = knots.iter();
     let mut previous = knots_iter.next().unwrap();
-    while let Some(next) = knots_iter.next() {
+    for next in knots_iter {
         if *previous >= *next {
             return Err(FittingError::KnotsNotStrictlyIncreasing(
                 String::from("Knots must be strictly increasing")
@@ -60,10 +158,65 @@ pub(crate) fn check_increasing<T: Float>(knots: &Vec<T>) -> Result<(), FittingEr
     Ok(())
 }
 
-pub(crate) fn check_matrix_size<T: Float>(matrix_1: &Vec<T>, matrix_2: &Vec<T>) -> Result<(), FittingError> {
+pub(crate) fn check_spline_parts<T: Float>(knots: &[T], coefficients: &[T], half_order: usize)
+    -> Result<(), FittingError> {
+    check_order(half_order, knots.len())?;
+    check_increasing(knots)?;
+    check_vector_length(coefficients, knots.len())?;
+    if !coefficients.iter().all(|coefficient| coefficient.is_finite()) {
+        return Err(FittingError::NonFiniteCoefficients(String::from("B-spline coefficients must be finite")));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+pub(crate) fn check_diagnostics<T: Float>(diagnostics: &FitDiagnostics<T>) -> Result<(), FittingError> {
+    let zero = T::from(0.).expect("Cannot convert to type from f64");
+    let non_negative = [diagnostics.gcv, diagnostics.mean_squared_residual, diagnostics.degrees_of_freedom,
+                        diagnostics.estimated_variance, diagnostics.smoothing_parameter];
+    if !non_negative.iter().all(|statistic| *statistic >= zero && statistic.is_finite()) {
+        return Err(FittingError::InvalidDiagnostics(String::from(
+            "GCV value, residual, degrees of freedom, variance and smoothing parameter must be finite and non-negative"
+        )));
+    }
+    if !diagnostics.mean_squared_error.is_finite() {
+        return Err(FittingError::InvalidDiagnostics(String::from("Mean squared error must be finite")));
+    }
+    if !(diagnostics.smoothing_ratio >= zero
+        && diagnostics.smoothing_ratio <= T::from(1.).expect("Cannot convert to type from f64")) {
+        return Err(FittingError::InvalidDiagnostics(String::from("Smoothing ratio must lie between 0 and 1")));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_fit_mode<T: Float>(mode: FitMode<T>, half_order: usize, num_knots: usize)
+    -> Result<(), FittingError> {
+    let zero = T::from(0.).expect("Cannot convert to type from f64");
+    match mode {
+        FitMode::KnownVariance(variance) if !(variance >= zero && variance.is_finite()) => {
+            Err(FittingError::InvalidFitMode(
+                "Error variance must be finite and non-negative".to_string()
+            ))
+        }
+        FitMode::SmoothingParameter(smoothing) if !(smoothing >= zero && smoothing.is_finite()) => {
+            Err(FittingError::InvalidFitMode(
+                "Smoothing parameter must be finite and non-negative".to_string()
+            ))
+        }
+        FitMode::DegreesOfFreedom(dof)
+            if !(dof >= zero && dof <= T::from(num_knots - half_order).expect("Cannot convert to type from usize")) => {
+            Err(FittingError::InvalidFitMode(format!(
+                "Degrees of freedom must lie between 0 and {}", num_knots - half_order
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+pub(crate) fn check_matrix_size<T: Float>(matrix_1: &[T], matrix_2: &[T]) -> Result<(), FittingError> {
     if matrix_1.len() != matrix_2.len() {
         return Err(FittingError::MatrixMismatch(
-            String::from(format!("Matrix size mismatch: {} and {}", matrix_1.len(), matrix_2.len()))
+            format!("Matrix size mismatch: {} and {}", matrix_1.len(), matrix_2.len())
         ));
     }
     Ok(())
@@ -1,11 +1,11 @@
 use num_traits::Float;
 use crate::woltring::support::{check_matrix_size, check_order, FittingError};
 
-pub(crate) fn trace_inverse<T: Float>(basis_tableau: &Vec<T>, mut decomp_matrix: Vec<T>, half_order: usize)
-    -> Result<(Vec<T>, T), FittingError> {
+pub(crate) fn trace_inverse<T: Float>(basis_tableau: &[T], decomp_matrix: &mut [T], half_order: usize)
+    -> Result<T, FittingError> {
     let num_knots = decomp_matrix.len() / (2 * half_order + 1);
     check_order(half_order, num_knots)?;
-    check_matrix_size(basis_tableau, &decomp_matrix)?;
+    check_matrix_size(basis_tableau, decomp_matrix)?;
 
     let mut inversion;
 
@@ -51,11 +51,13 @@ pub(crate) fn trace_inverse<T: Float>(basis_tableau: &Vec<T>, mut decomp_matrix:
     // Trace and zero portions of inverted matrix
     let mut trace = T::from(0.).expect("Cannot convert to type from f64");
     for knot_index in 1 ..= num_knots {
-        let lower_bound = half_order.min(knot_index - 1);
-        let upper_bound = half_order.min(num_knots - knot_index);
+        let lower_bound = -(half_order.min(knot_index - 1) as i32);
+        let upper_bound = half_order.min(num_knots - knot_index) as i32;
         for idx in lower_bound ..= upper_bound {
-            trace = trace + (basis_tableau[(knot_index - 1) * (half_order * 2 + 1) + idx + half_order] *
-                decomp_matrix[(idx + knot_index - 1) * (half_order * 2 + 1) - idx + half_order]);
+            trace = trace + (basis_tableau[((knot_index as i32 - 1) * (half_order as i32 * 2 + 1) + idx
+                + half_order as i32) as usize] *
+                decomp_matrix[((idx + knot_index as i32 - 1) * (half_order as i32 * 2 + 1) - idx
+                    + half_order as i32) as usize]);
         }
     }
     for order_index in 1 ..= half_order {
@@ -64,5 +66,5 @@ pub(crate) fn trace_inverse<T: Float>(basis_tableau: &Vec<T>, mut decomp_matrix:
         decomp_matrix[half_order - order_index] = T::from(0.).expect("Cannot convert to type from f64");
     }
 
-    Ok((decomp_matrix, trace))
+    Ok(trace)
 }
\ No newline at end of file
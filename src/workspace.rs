@@ -0,0 +1,60 @@
+use num_traits::Float;
+use crate::woltring::gcvspl::{fit_gcv_spline_into, workspace_len};
+use crate::woltring::search::walk_knot_interval;
+use crate::woltring::splder::evaluate_spline_into;
+use crate::woltring::support::{check_spline_parts, check_workspace, FitDiagnostics, FitMode, FittingError};
+
+/// Returns the number of elements of the workspace needed by [`fit_with_workspace`] to fit `num_knots` data points
+/// with a half-order of `half_order`. This is (6 * *m* + 1) * *n* + 2 * *m* for *n* knots and a half-order *m*.
+pub fn required_workspace(num_knots: usize, half_order: usize) -> usize {
+    workspace_len(num_knots, half_order)
+}
+
+/// Returns the number of elements of the workspace needed by [`evaluate_with_workspace`] to evaluate a spline with a
+/// half-order of `half_order`. This is 2 * *m* for a half-order *m*.
+pub fn required_evaluation_workspace(half_order: usize) -> usize {
+    2 * half_order
+}
+
+/// Fits a GCV spline like [`GcvSpline::from_full_parameters_and_mode`](crate::GcvSpline::from_full_parameters_and_mode)
+/// without allocating on the heap. The B-spline coefficients, one per knot, are written into `coefficients`, and
+/// `workspace` holds the design matrices and all other working storage. It must have at least
+/// [`required_workspace`] elements and can be reused between fits. The coefficients can be evaluated with
+/// [`evaluate_with_workspace`], or with [`GcvSpline::from_parts`](crate::GcvSpline::from_parts) where allocation is
+/// possible. Only a successful fit is free of allocation: the returned [`FittingError`] holds a `String` describing
+/// the error.
+pub fn fit_with_workspace<T: Float>(time: &[T], data: &[T], weights: &[T], half_order: usize, mode: FitMode<T>,
+                                    coefficients: &mut [T], workspace: &mut [T])
+    -> Result<FitDiagnostics<T>, FittingError> {
+    fit_gcv_spline_into(time, data, weights, half_order, mode, coefficients, workspace)
+}
+
+/// Evaluates a derivative of a given order of the spline described by `knots`, `coefficients` and `half_order`, such
+/// as one fitted by [`fit_with_workspace`], without allocating on the heap. The values at each point yielded by
+/// `points` are written into `output` in the same order, and `workspace` must have at least
+/// [`required_evaluation_workspace`] elements. Returns the number of values written; points beyond the length of
+/// `output` are not evaluated. Points outside the knots continue the polynomial pieces at either end, as with
+/// [`Extrapolation::Polynomial`](crate::Extrapolation::Polynomial), and NaN points give NaN. The spline is checked
+/// like in [`GcvSpline::from_parts`](crate::GcvSpline::from_parts), and as in [`fit_with_workspace`], the returned
+/// [`FittingError`] holds a `String` describing the error.
+pub fn evaluate_with_workspace<T: Float, I: IntoIterator<Item = T>>(knots: &[T], coefficients: &[T],
+                                                                    half_order: usize, points: I,
+                                                                    derivative_order: usize, output: &mut [T],
+                                                                    workspace: &mut [T])
+    -> Result<usize, FittingError> {
+    check_spline_parts(knots, coefficients, half_order)?;
+    check_workspace(workspace, required_evaluation_workspace(half_order))?;
+
+    let mut knot_interval = 0;
+    let mut written = 0;
+    for (value, point) in output.iter_mut().zip(points) {
+        *value = if point.is_nan() {
+            point
+        } else {
+            knot_interval = walk_knot_interval(knots, point, knot_interval);
+            evaluate_spline_into(derivative_order, half_order, point, knots, coefficients, knot_interval, workspace)
+        };
+        written += 1;
+    }
+    Ok(written)
+}